@@ -2,6 +2,8 @@ extends Control
 
 @onready var history = $History
 
+const SAVE_PATH = "user://history.json"
+
 @onready var textbox = get_node("TextArea/Margins/TextBox")
 @onready var text = []
 @onready var i = 0
@@ -61,7 +63,10 @@ func _ready():
 	
 		
 	$TextArea.set_visible(false)
-	history.generate_history()
+	# reopen the same shop as last time, if there is one
+	if not (FileAccess.file_exists(SAVE_PATH) and history.load_from(SAVE_PATH)):
+		history.generate_history()
+		history.save_to(SAVE_PATH)
 	for i in range(6):
 		var item = history.get_item(i)
 		emit_signal("loadResource", "Item"+str(i+1), sprites[item.item_type])
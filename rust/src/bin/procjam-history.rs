@@ -0,0 +1,462 @@
+// generates and inspects histories without launching godot.
+// run from the rust directory with e.g. `cargo run --bin procjam-history -- item 2 --seed 42 --stories`
+
+use procjam_2024::config::WorldConfig;
+use procjam_2024::error::WorldError;
+use procjam_2024::export::*;
+use procjam_2024::graph::RoadDirection;
+use procjam_2024::narration::*;
+use procjam_2024::world::*;
+use rand::{thread_rng, Rng};
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+// the game is run from the godot directory, so its writing lives there
+const WORLD_CONFIG_PATH: &str = "../godot/writing/world_config.toml";
+const DESCRIPTIONS_PATH: &str = "../godot/writing/descriptions.json";
+
+const USAGE: &str = "usage: procjam-history <command> [options]
+
+commands:
+    generate            print every city, character and event
+    items               list the items and who owns them at the end
+    item <id>           print an item's description and records
+    character <id>      print a character and the events they were in
+    city <id>           print a city, its roads and the events that happened there
+    export <dir>        write the map and every item's journey to <dir> as .dot and .svg files
+
+options:
+    --seed <n>          the seed to generate the world from (random if not given)
+    --config <path>     the world config file (default ../godot/writing/world_config.toml)
+    --descriptions <path>
+                        the descriptions file (default ../godot/writing/descriptions.json)
+    --stories           also narrate the item's stories (item only)
+    --verbose           log generation to stderr";
+
+enum Command {
+    Generate,
+    Items,
+    Item(ItemID),
+    Character(CharacterID),
+    City(CityID),
+    Export(String),
+}
+
+struct Args {
+    command: Command,
+    seed: Option<u64>,
+    config_path: Option<String>,
+    descriptions_path: String,
+    stories: bool,
+    verbose: bool,
+}
+
+fn parse_id(arg: Option<String>, what: &str) -> Result<usize, String> {
+    let arg = arg.ok_or(format!("missing {} id", what))?;
+    arg.parse()
+        .map_err(|_| format!("{:?} is not a valid {} id", arg, what))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("generate") => Command::Generate,
+        Some("items") => Command::Items,
+        Some("item") => Command::Item(ItemID(parse_id(args.next(), "item")?)),
+        Some("character") => Command::Character(CharacterID(parse_id(args.next(), "character")?)),
+        Some("city") => Command::City(CityID(parse_id(args.next(), "city")?)),
+        Some("export") => Command::Export(args.next().ok_or("missing export directory")?),
+        Some(other) => return Err(format!("unknown command {:?}", other)),
+        None => return Err("missing command".to_string()),
+    };
+
+    let mut parsed = Args {
+        command,
+        seed: None,
+        config_path: None,
+        descriptions_path: DESCRIPTIONS_PATH.to_string(),
+        stories: false,
+        verbose: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let seed = args.next().ok_or("missing value for --seed")?;
+                parsed.seed = Some(
+                    seed.parse()
+                        .map_err(|_| format!("{:?} is not a valid seed", seed))?,
+                );
+            }
+            "--config" => {
+                parsed.config_path = Some(args.next().ok_or("missing value for --config")?);
+            }
+            "--descriptions" => {
+                parsed.descriptions_path = args.next().ok_or("missing value for --descriptions")?;
+            }
+            "--stories" => parsed.stories = true,
+            "--verbose" => parsed.verbose = true,
+            other => return Err(format!("unknown option {:?}", other)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// -- Logging --
+
+// prints log records to stderr so they don't get mixed up with the history itself
+struct StderrLogger;
+
+static STDERR_LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// -- Printing --
+
+fn character_name(world: &World, character_id: CharacterID) -> String {
+    match world.characters.get(&character_id) {
+        Some(character) => format!("{} (#{})", character.name, character_id.0),
+        None => format!("#{}", character_id.0),
+    }
+}
+
+// the owner of an item after a record, or nobody if it was lost
+fn owner_name(world: &World, record: &ItemMoveRecord) -> String {
+    match record.new_owner {
+        Some(owner_id) => character_name(world, owner_id),
+        None => "nobody".to_string(),
+    }
+}
+
+fn city_name(world: &World, city_id: CityID) -> String {
+    match world.cities.get(&city_id) {
+        Some(city) => format!("{} (#{})", city.name, city_id.0),
+        None => format!("#{}", city_id.0),
+    }
+}
+
+fn print_event(world: &World, event_id: EventID) -> Result<(), WorldError> {
+    let event = world
+        .events
+        .get(&event_id)
+        .ok_or(WorldError::MissingEvent(event_id))?;
+    // journeys and other events that take time show when they ended
+    let time = match event.end_time {
+        Some(end_time) => format!("{}-{}", event.start_time, end_time),
+        None => event.start_time.to_string(),
+    };
+    println!("    [{}] #{} {}", time, event_id.0, event.summary);
+    Ok(())
+}
+
+fn print_world(world: &World) -> Result<(), WorldError> {
+    println!("cities:");
+    for (layer_i, layer) in world.roads.layers().iter().enumerate() {
+        println!("  layer {}:", layer_i);
+        for &city_id in layer {
+            // only show the roads leading onwards, so each road is shown once
+            let onward: Vec<String> = world
+                .roads
+                .onward(city_id)
+                .into_iter()
+                .map(|next_id| city_name(world, next_id))
+                .collect();
+            println!("    {} -> {}", city_name(world, city_id), onward.join(", "));
+        }
+    }
+
+    println!("characters:");
+    for (&character_id, character) in &world.characters {
+        let status = if character.has_died(world) {
+            "dead"
+        } else {
+            "alive"
+        };
+        println!("    {} ({})", character_name(world, character_id), status);
+    }
+
+    println!("events:");
+    for &event_id in world.events.keys() {
+        print_event(world, event_id)?;
+    }
+
+    Ok(())
+}
+
+fn print_items(world: &World) -> Result<(), WorldError> {
+    for (&item_id, item) in &world.items {
+        let newest_record = item
+            .owner_records
+            .last()
+            .ok_or(WorldError::IncompleteRecord("owner"))?;
+        println!(
+            "#{} {} owned by {} in {}",
+            item_id.0,
+            item.item_type,
+            owner_name(world, newest_record),
+            city_name(world, newest_record.location()?)
+        );
+    }
+
+    Ok(())
+}
+
+fn print_item(
+    world: &World,
+    item_id: ItemID,
+    descs: &DescJson,
+    stories: bool,
+) -> Result<(), Box<dyn Error>> {
+    let item = world
+        .items
+        .get(&item_id)
+        .ok_or(WorldError::MissingItem(item_id))?;
+
+    // narrate the whole world so the item gets the same lines as it would in the game
+    let narration = narrate_world(world, descs)?
+        .into_iter()
+        .find(|narration| narration.item_id == item_id)
+        .ok_or(WorldError::MissingItem(item_id))?;
+
+    println!("#{} {}", item_id.0, narration.item_type);
+    for line in &narration.description {
+        println!("    {}", line);
+    }
+
+    println!("records:");
+    let mut last_city = None;
+    for record in &item.owner_records {
+        let city_id = record.location()?;
+        let travelled = match last_city {
+            Some(last_city_id) => world.distance_between(last_city_id, city_id)?,
+            None => 0.0,
+        };
+        last_city = Some(city_id);
+        println!(
+            "    [{}] {} in {} (travelled {:.0})",
+            record.time,
+            owner_name(world, record),
+            city_name(world, city_id),
+            travelled
+        );
+        if let Some(event_id) = record.event {
+            print_event(world, event_id)?;
+        }
+    }
+
+    if stories {
+        for (story_i, story) in narration.stories.iter().enumerate() {
+            println!("story {}:", story_i);
+            for line in story {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_character(world: &World, character_id: CharacterID) -> Result<(), WorldError> {
+    let character = world
+        .characters
+        .get(&character_id)
+        .ok_or(WorldError::MissingCharacter(character_id))?;
+    let pronouns = &character.pronouns;
+    println!(
+        "{} ({}/{}/{})",
+        character_name(world, character_id),
+        pronouns.nominative,
+        pronouns.accusative,
+        pronouns.dep_genitive
+    );
+    let personality = &character.personality;
+    println!(
+        "wanderlust {:?}, sociability {:?}, greed {:?}, caution {:?}, sentimentality {:?}",
+        personality.wanderlust,
+        personality.sociability,
+        personality.greed,
+        personality.caution,
+        personality.sentimentality
+    );
+    println!(
+        "born in {}, with a lifespan of {}",
+        character.born, character.lifespan
+    );
+    if let Some(cause) = character.death_cause(world) {
+        println!("died {}", cause);
+    }
+
+    if !character.relationships.is_empty() {
+        println!("relationships:");
+        for (&other_id, relationship) in &character.relationships {
+            println!(
+                "  {:?} of {}, met {} times",
+                relationship.kind(),
+                character_name(world, other_id),
+                relationship.encounters
+            );
+        }
+    }
+
+    println!("events:");
+    for &event_id in &character.events {
+        print_event(world, event_id)?;
+    }
+
+    Ok(())
+}
+
+fn print_city(world: &World, city_id: CityID) -> Result<(), WorldError> {
+    let city = world
+        .cities
+        .get(&city_id)
+        .ok_or(WorldError::MissingCity(city_id))?;
+    println!("{}", city_name(world, city_id));
+    println!(
+        "{} ({} culture)",
+        city.traits.describe(),
+        city.traits.culture
+    );
+
+    if let Some(layer) = world.roads.layer_of(city_id) {
+        println!("layer {}", layer);
+    }
+    println!("at ({}, {})", city.position.x, city.position.y);
+    if city.founded > 0 {
+        println!("founded in {}", city.founded);
+    }
+    for former_name in &city.former_names {
+        println!("called {} until {}", former_name.name, former_name.until);
+    }
+    match (city.calamity_arrived, city.calamity_receded) {
+        (Some(arrived), Some(receded)) => {
+            println!("calamity from {} until {}", arrived, receded)
+        }
+        (Some(arrived), None) => println!("calamity since {}", arrived),
+        _ => (),
+    }
+    match (city.abandoned, city.ruined) {
+        (Some(abandoned), Some(ruined)) => {
+            println!("abandoned in {}, in ruins since {}", abandoned, ruined)
+        }
+        (Some(abandoned), None) => println!("abandoned in {}", abandoned),
+        _ => (),
+    }
+
+    println!("roads:");
+    for road in world.roads.roads_at(city_id) {
+        let outgoing = road.from == city_id;
+        let other_id = if outgoing { road.to } else { road.from };
+        let arrow = match road.direction {
+            RoadDirection::TwoWay => "<->",
+            RoadDirection::OneWay if outgoing => "->",
+            RoadDirection::OneWay => "<-",
+        };
+        println!(
+            "    {} {} (length {})",
+            arrow,
+            city_name(world, other_id),
+            road.length
+        );
+    }
+
+    println!("events:");
+    for &event_id in &city.events {
+        print_event(world, event_id)?;
+    }
+
+    Ok(())
+}
+
+fn export_world(world: &World, dir: &str) -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("map.dot"), world_to_dot(world)?)?;
+    fs::write(dir.join("map.svg"), world_to_svg(world)?)?;
+    for &item_id in world.items.keys() {
+        fs::write(
+            dir.join(format!("item_{}.dot", item_id.0)),
+            item_to_dot(world, item_id)?,
+        )?;
+        fs::write(
+            dir.join(format!("item_{}.svg", item_id.0)),
+            item_to_svg(world, item_id)?,
+        )?;
+    }
+    println!(
+        "wrote the map and {} items to {}",
+        world.items.len(),
+        dir.display()
+    );
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let config = match &args.config_path {
+        Some(path) => WorldConfig::load(path)?,
+        None if Path::new(WORLD_CONFIG_PATH).exists() => WorldConfig::load(WORLD_CONFIG_PATH)?,
+        None => WorldConfig::default(),
+    };
+    // seeds are kept within i64 so they can be pasted into the History node's seed property
+    let seed = args
+        .seed
+        .unwrap_or_else(|| thread_rng().gen_range(0..i64::MAX as u64));
+
+    let mut world = World::generate_world_with_seed(config, seed)?;
+    world.generate_events()?;
+    // printed so a random history can be regenerated with --seed
+    println!("seed: {}", seed);
+
+    match args.command {
+        Command::Generate => print_world(&world)?,
+        Command::Items => print_items(&world)?,
+        Command::Item(item_id) => {
+            let descs = load_descriptions(&args.descriptions_path)?;
+            print_item(&world, item_id, &descs, args.stories)?;
+        }
+        Command::Character(character_id) => print_character(&world, character_id)?,
+        Command::City(city_id) => print_city(&world, city_id)?,
+        Command::Export(dir) => export_world(&world, &dir)?,
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.verbose && log::set_logger(&STDERR_LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Trace);
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
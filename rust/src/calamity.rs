@@ -0,0 +1,671 @@
+use crate::config::WorldConfig;
+use crate::error::WorldError;
+use crate::graph::RoadGraph;
+use crate::world::{CharacterID, CharacterState, CityID, Personality, LOG_CALAMITY};
+use log::{info, trace, warn};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// -- Calamities --
+
+// what drives everyone from the start city towards the end of the road.
+// a calamity decides how it spreads from city to city, how it weighs on the characters caught in it,
+// and what the narration calls it.
+pub trait Calamity {
+    // what the shopkeeper calls it, e.g. "the fire"
+    fn name(&self) -> &'static str;
+
+    // reaches new cities. by default the calamity starts in the city it broke out in,
+    // then spreads from city to city along the roads
+    fn spread(
+        &self,
+        time: usize,
+        state: &mut CalamityState,
+        roads: &RoadGraph,
+        rng: &mut dyn RngCore,
+    ) {
+        if time == state.start_time {
+            state.reach(state.start_city, time);
+        }
+        spread_along_roads(self, time, state, roads, rng);
+    }
+
+    // how long the calamity has to have been in a city before it can spread down a road of this length
+    fn spread_delay(&self, freq: usize, road_length: usize) -> usize {
+        freq * road_length
+    }
+
+    // the weight of dying for a character who has been in the calamity for this long
+    fn death_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness
+    }
+
+    // the extra weight of leaving a city the calamity has been in for this long
+    fn move_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness
+    }
+
+    // chance each time step of each item carried in the calamity being lost
+    fn item_loss_chance(&self) -> f64 {
+        0.0
+    }
+
+    // whether characters catch the calamity from each other and carry it to the cities they travel to
+    fn spreads_by_contact(&self) -> bool {
+        false
+    }
+}
+
+// once the calamity has been in a city for long enough, it has a chance each time step of spreading
+// down each road out of the city. so different branches of the map fall at different times,
+// and the calamity is slower to cross long roads
+fn spread_along_roads<C: Calamity + ?Sized>(
+    calamity: &C,
+    time: usize,
+    state: &mut CalamityState,
+    roads: &RoadGraph,
+    rng: &mut dyn RngCore,
+) {
+    let mut newly_reached: Vec<CityID> = Vec::new();
+    for (&city_id, &reached_at) in &state.reached {
+        for road in roads.roads() {
+            let next_city = match road.leads_from(city_id) {
+                Some(next_city) => next_city,
+                None => continue,
+            };
+            if state.reached.contains_key(&next_city) || newly_reached.contains(&next_city) {
+                continue;
+            }
+            let spreads_at = reached_at + calamity.spread_delay(state.freq, road.length);
+            if time >= spreads_at && rng.gen_bool(state.spread_chance) {
+                newly_reached.push(next_city);
+            }
+        }
+    }
+    for city_id in newly_reached {
+        state.reach(city_id, time);
+    }
+}
+
+// spreads quickly and kills quickly
+pub struct Fire;
+
+const FIRE_SPEED: usize = 2;
+const FIRE_FEROCITY: usize = 2;
+
+impl Calamity for Fire {
+    fn name(&self) -> &'static str {
+        "the fire"
+    }
+
+    fn spread_delay(&self, freq: usize, road_length: usize) -> usize {
+        (freq * road_length / FIRE_SPEED).max(1)
+    }
+
+    fn death_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness * FIRE_FEROCITY
+    }
+}
+
+// spreads more slowly than the fire and drowns fewer people, but washes away what they're carrying
+pub struct Flood;
+
+const FLOOD_ITEM_LOSS_CHANCE: f64 = 0.2;
+
+impl Calamity for Flood {
+    fn name(&self) -> &'static str {
+        "the flood"
+    }
+
+    fn death_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness / 2
+    }
+
+    fn item_loss_chance(&self) -> f64 {
+        FLOOD_ITEM_LOSS_CHANCE
+    }
+}
+
+// starts with one person falling ill, and spreads to whoever they meet and wherever they go
+pub struct Plague;
+
+impl Calamity for Plague {
+    fn name(&self) -> &'static str {
+        "the plague"
+    }
+
+    // the first character falls ill when the plague breaks out, wherever they've got to.
+    // if nobody was there to fall ill, the plague is in the city itself
+    fn spread(
+        &self,
+        time: usize,
+        state: &mut CalamityState,
+        _roads: &RoadGraph,
+        _rng: &mut dyn RngCore,
+    ) {
+        if time != state.start_time {
+            return;
+        }
+        match state.patient_zero {
+            Some(patient_zero) => {
+                state.infected.insert(patient_zero);
+            }
+            None => state.reach(state.start_city, time),
+        }
+    }
+
+    fn spreads_by_contact(&self) -> bool {
+        true
+    }
+}
+
+// creeps along slowly and rarely kills outright, but drives everyone it reaches to move on
+pub struct Famine;
+
+const FAMINE_SLOWNESS: usize = 2;
+const FAMINE_MIGRATION: usize = 3;
+
+impl Calamity for Famine {
+    fn name(&self) -> &'static str {
+        "the famine"
+    }
+
+    fn spread_delay(&self, freq: usize, road_length: usize) -> usize {
+        freq * road_length * FAMINE_SLOWNESS
+    }
+
+    fn death_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness / 2
+    }
+
+    fn move_weight(&self, calamity_time: usize, deadliness: usize) -> usize {
+        calamity_time * deadliness * FAMINE_MIGRATION
+    }
+}
+
+// which calamity a world's history is generated with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CalamityKind {
+    #[default]
+    Fire,
+    Flood,
+    Plague,
+    Famine,
+}
+
+impl CalamityKind {
+    pub const ALL: [CalamityKind; 4] = [
+        CalamityKind::Fire,
+        CalamityKind::Flood,
+        CalamityKind::Plague,
+        CalamityKind::Famine,
+    ];
+
+    pub fn calamity(&self) -> &'static dyn Calamity {
+        match self {
+            CalamityKind::Fire => &Fire,
+            CalamityKind::Flood => &Flood,
+            CalamityKind::Plague => &Plague,
+            CalamityKind::Famine => &Famine,
+        }
+    }
+}
+
+// -- Calamity state --
+
+// something the calamity did to a city, for the world to record as an event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalamityMilestone {
+    Arrived(CityID), // the calamity reached the city
+    Fell(CityID),    // the last person left in the city died or fled from the calamity
+    Receded(CityID), // the calamity burnt out, drained away or moved on from the city
+}
+
+// the state of one calamity
+pub struct CalamityState {
+    pub kind: CalamityKind,
+    pub start_city: CityID,                   // where the calamity breaks out
+    pub start_time: usize,                    // when the calamity breaks out
+    pub patient_zero: Option<CharacterID>, // who falls ill first, for calamities that spread by contact
+    pub city_states: BTreeMap<CityID, usize>, // how long each city has been "in calamity"
+    pub freq: usize, // how long the calamity takes to spread down a road of length 1
+    pub deadliness: usize, // how quickly the calamity's kill probability increases
+    pub spread_chance: f64, // chance each time step of the calamity spreading down a road it's able to
+    pub reached: BTreeMap<CityID, usize>, // the cities the calamity has got to, and when it got there
+    pub infected: BTreeSet<CharacterID>, // characters carrying the calamity, for calamities that spread by contact
+    pub duration: usize,                 // how long the calamity stays in a city before receding
+    pub receded: BTreeSet<CityID>, // the cities the calamity has left, which it can't come back to
+    pub ended: Option<usize>,      // when the calamity had left everywhere it got to, if it has
+    holding_out: BTreeSet<CityID>, // cities in the calamity that still have someone alive in them
+    milestones: Vec<CalamityMilestone>, // milestones that haven't been recorded yet
+}
+
+impl CalamityState {
+    pub fn new(
+        kind: CalamityKind,
+        start_city: CityID,
+        start_time: usize,
+        patient_zero: Option<CharacterID>,
+        cities: Vec<&CityID>,
+        config: &WorldConfig,
+    ) -> Self {
+        let mut state = CalamityState {
+            kind,
+            start_city,
+            start_time,
+            patient_zero,
+            city_states: BTreeMap::new(),
+            freq: config.calamity_freq,
+            deadliness: config.calamity_deadliness,
+            spread_chance: config.calamity_spread_chance,
+            reached: BTreeMap::new(),
+            infected: BTreeSet::new(),
+            duration: config.calamity_duration,
+            receded: BTreeSet::new(),
+            ended: None,
+            holding_out: BTreeSet::new(),
+            milestones: Vec::new(),
+        };
+
+        for &city in cities {
+            state.city_states.insert(city, 0);
+        }
+
+        state
+    }
+
+    pub fn calamity(&self) -> &'static dyn Calamity {
+        self.kind.calamity()
+    }
+
+    // the weights this calamity adds to moving on from and dying in a city
+    fn city_weights(&self, city_id: CityID) -> Result<(usize, usize), WorldError> {
+        let &city_state = self
+            .city_states
+            .get(&city_id)
+            .ok_or(WorldError::MissingCity(city_id))?;
+        let calamity = self.calamity();
+        Ok((
+            calamity.move_weight(city_state, self.deadliness),
+            calamity.death_weight(city_state, self.deadliness),
+        ))
+    }
+
+    // the calamity gets to a city, if it isn't there already and hasn't already been and gone
+    pub fn reach(&mut self, city_id: CityID, time: usize) {
+        if self.ended.is_some()
+            || self.reached.contains_key(&city_id)
+            || self.receded.contains(&city_id)
+        {
+            return;
+        }
+        self.reached.insert(city_id, time);
+        self.milestones.push(CalamityMilestone::Arrived(city_id));
+    }
+
+    // checks which cities in the calamity still have someone alive in them.
+    // a city falls when the last of the people holding out in it die or flee
+    fn check_holdouts(
+        &mut self,
+        character_states: &[CharacterState],
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+    ) {
+        for &city_id in self.reached.keys() {
+            let someone_alive = city_populations.get(&city_id).is_some_and(|population| {
+                population.iter().any(|&CharacterID(id)| {
+                    character_states.get(id).is_some_and(|state| !state.dead)
+                })
+            });
+            if someone_alive {
+                self.holding_out.insert(city_id);
+            } else if self.holding_out.remove(&city_id) {
+                self.milestones.push(CalamityMilestone::Fell(city_id));
+            }
+        }
+    }
+
+    // two characters met. if either of them is carrying the calamity, now they both are
+    fn contact(&mut self, characters: &[CharacterID]) {
+        if !self.calamity().spreads_by_contact() || self.ended.is_some() {
+            return;
+        }
+        if characters
+            .iter()
+            .any(|character| self.infected.contains(character))
+        {
+            self.infected.extend(characters);
+        }
+    }
+
+    // a character arrived in a city, bringing the calamity with them if they're carrying it
+    fn arrival(&mut self, time: usize, character: CharacterID, city_id: CityID) {
+        let new_to_city = !self.reached.contains_key(&city_id) && !self.receded.contains(&city_id);
+        if self.infected.contains(&character) && new_to_city && self.ended.is_none() {
+            self.reach(city_id, time);
+            info!(
+                target: LOG_CALAMITY,
+                "character {:?} brought {} to city {:?}",
+                character,
+                self.calamity().name(),
+                city_id
+            );
+        }
+    }
+
+    // spreads the calamity and lets it leave the cities it's been in for long enough.
+    // returns the cities it left
+    fn calamity_step<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        roads: &RoadGraph,
+        character_states: &[CharacterState],
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+        mut rng: &mut R,
+    ) -> Result<Vec<CityID>, WorldError> {
+        if self.ended.is_some() {
+            return Ok(Vec::new());
+        }
+        let calamity = self.calamity();
+        calamity.spread(time, self, roads, &mut rng);
+
+        // anywhere someone alive carrying the calamity is, the calamity is too
+        for (&city_id, city_population) in city_populations {
+            if city_population.iter().any(|character| {
+                self.infected.contains(character)
+                    && character_states
+                        .get(character.0)
+                        .is_some_and(|state| !state.dead)
+            }) {
+                self.reach(city_id, time);
+            }
+        }
+
+        // increase calamity time of each city in calamity
+        for &city_id in self.reached.keys() {
+            let prev_city_state = self
+                .city_states
+                .get(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?;
+            self.city_states.insert(city_id, prev_city_state + 1);
+        }
+
+        // the calamity leaves cities it's been in for long enough
+        let receding: Vec<CityID> = self
+            .reached
+            .keys()
+            .copied()
+            .filter(|city_id| self.city_states.get(city_id) > Some(&self.duration))
+            .collect();
+        for &city_id in &receding {
+            self.reached.remove(&city_id);
+            self.receded.insert(city_id);
+            self.holding_out.remove(&city_id);
+            self.city_states.insert(city_id, 0);
+            self.milestones.push(CalamityMilestone::Receded(city_id));
+        }
+
+        // once it's left everywhere it got to, it's over
+        if !receding.is_empty() && self.reached.is_empty() {
+            self.ended = Some(time);
+            self.infected.clear();
+            info!(target: LOG_CALAMITY, "{} is over", self.calamity().name());
+        }
+
+        Ok(receding)
+    }
+}
+
+// someone living in the city a calamity breaks out in, to fall ill first if it spreads by contact
+pub fn choose_patient_zero<R: Rng + ?Sized>(
+    city_id: CityID,
+    character_states: &[CharacterState],
+    rng: &mut R,
+) -> Option<CharacterID> {
+    let residents: Vec<CharacterID> = character_states
+        .iter()
+        .filter(|state| !state.dead && state.journey.is_none() && state.city == city_id)
+        .map(|state| state.character)
+        .collect();
+    residents.choose(rng).copied()
+}
+
+// every calamity that has broken out over the world's history, in the order they broke out
+pub struct Calamities {
+    pub states: Vec<CalamityState>,
+}
+
+impl Calamities {
+    // the first calamity breaks out in the start city after calamity_freq time steps
+    pub fn new<R: Rng + ?Sized>(
+        cities: Vec<&CityID>,
+        roads: &RoadGraph,
+        config: &WorldConfig,
+        character_states: &[CharacterState],
+        rng: &mut R,
+    ) -> Result<Self, WorldError> {
+        let start_city = roads.start_city().ok_or(WorldError::NoStartCity)?;
+        let first = CalamityState::new(
+            config.calamity,
+            start_city,
+            config.calamity_freq,
+            choose_patient_zero(start_city, character_states, rng),
+            cities,
+            config,
+        );
+        Ok(Calamities {
+            states: vec![first],
+        })
+    }
+
+    // another calamity breaks out, starting now
+    pub fn break_out(
+        &mut self,
+        kind: CalamityKind,
+        start_city: CityID,
+        time: usize,
+        patient_zero: Option<CharacterID>,
+        roads: &RoadGraph,
+        config: &WorldConfig,
+    ) {
+        let cities: Vec<&CityID> = roads.layers().iter().flatten().collect();
+        info!(
+            target: LOG_CALAMITY,
+            "{} broke out in city {:?}",
+            kind.calamity().name(),
+            start_city
+        );
+        self.states.push(CalamityState::new(
+            kind,
+            start_city,
+            time,
+            patient_zero,
+            cities,
+            config,
+        ));
+    }
+
+    // whether any calamity is in a city right now
+    pub fn is_in(&self, city_id: CityID) -> bool {
+        self.states
+            .iter()
+            .any(|state| state.reached.contains_key(&city_id))
+    }
+
+    // how long the city has been in whichever calamity has been there longest
+    pub fn calamity_time(&self, city_id: CityID) -> usize {
+        self.states
+            .iter()
+            .filter_map(|state| state.city_states.get(&city_id))
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    // a new city was founded, out of every calamity's reach for now
+    pub fn add_city(&mut self, city_id: CityID) {
+        for state in self.states.iter_mut() {
+            state.city_states.insert(city_id, 0);
+        }
+    }
+
+    // the weights of moving on from and dying in a city, for a character who has just got there.
+    // calamities in the same city add up
+    pub fn city_weights(
+        &self,
+        city_id: CityID,
+        population: usize,
+        personality: &Personality,
+        roads: &RoadGraph,
+    ) -> Result<(usize, usize), WorldError> {
+        let mut flee_weight = 0;
+        let mut death_weight = 0;
+        for state in &self.states {
+            let (calamity_move, calamity_death) = state.city_weights(city_id)?;
+            flee_weight += calamity_move;
+            death_weight += calamity_death;
+        }
+        let mut move_weight =
+            personality.move_weight(population / 4) + personality.flee_weight(flee_weight);
+        // there's no escaping the calamity from the end of the road
+        if roads.onward(city_id).is_empty() {
+            move_weight = 0;
+        }
+        Ok((move_weight, death_weight))
+    }
+
+    // everything the calamities have done since this was last called, and which calamity did it
+    pub fn take_milestones(&mut self) -> Vec<(CalamityKind, CalamityMilestone)> {
+        self.states
+            .iter_mut()
+            .flat_map(|state| {
+                let kind = state.kind;
+                std::mem::take(&mut state.milestones)
+                    .into_iter()
+                    .map(move |milestone| (kind, milestone))
+            })
+            .collect()
+    }
+
+    pub fn check_holdouts(
+        &mut self,
+        character_states: &[CharacterState],
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+    ) {
+        for state in self.states.iter_mut() {
+            state.check_holdouts(character_states, city_populations);
+        }
+    }
+
+    // the furthest layer any ongoing calamity has reached, if they've reached anywhere
+    pub fn furthest_layer(&self, roads: &RoadGraph) -> Option<usize> {
+        self.states
+            .iter()
+            .flat_map(|state| state.reached.keys())
+            .filter_map(|&city_id| roads.layer_of(city_id))
+            .max()
+    }
+
+    // the dead can't pass anything on, or catch it
+    pub fn contact(&mut self, characters: &[CharacterID], character_states: &[CharacterState]) {
+        let living: Vec<CharacterID> = characters
+            .iter()
+            .copied()
+            .filter(|character| {
+                character_states
+                    .get(character.0)
+                    .is_some_and(|state| !state.dead)
+            })
+            .collect();
+        for state in self.states.iter_mut() {
+            state.contact(&living);
+        }
+    }
+
+    pub fn arrival(&mut self, time: usize, character: CharacterID, city_id: CityID) {
+        for state in self.states.iter_mut() {
+            state.arrival(time, character, city_id);
+        }
+    }
+
+    pub fn calamity_step<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        roads: &RoadGraph,
+        character_states: &mut [CharacterState],
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        let mut receding: BTreeSet<CityID> = BTreeSet::new();
+        for state in self.states.iter_mut() {
+            receding.extend(state.calamity_step(
+                time,
+                roads,
+                character_states,
+                city_populations,
+                rng,
+            )?);
+        }
+
+        // update death probabilities of each character in calamity, and of everyone one's just left
+        let mut changed: BTreeSet<CityID> = receding;
+        for state in &self.states {
+            changed.extend(state.reached.keys());
+        }
+        for city_id in changed {
+            self.update_weights(city_id, roads, character_states, city_populations)?;
+        }
+
+        Ok(())
+    }
+
+    fn update_weights(
+        &self,
+        city_id: CityID,
+        roads: &RoadGraph,
+        character_states: &mut [CharacterState],
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+    ) -> Result<(), WorldError> {
+        let city_population = city_populations
+            .get(&city_id)
+            .ok_or(WorldError::MissingCity(city_id))?;
+        for &CharacterID(id) in city_population {
+            let char_state = character_states
+                .get_mut(id)
+                .ok_or(WorldError::MissingCharacter(CharacterID(id)))?;
+            // dont update states if character is already dead
+            if char_state.dead {
+                continue;
+            }
+            let city_pop_without_self = city_population.len() - 1;
+            let (new_move_prob, new_death_prob) = self.city_weights(
+                city_id,
+                city_pop_without_self,
+                &char_state.personality,
+                roads,
+            )?;
+            trace!(
+                target: LOG_CALAMITY,
+                "next death prob for char {:?}: {:?}",
+                id,
+                new_death_prob
+            );
+            let weight_update = [(0, &new_move_prob), (1, &new_death_prob)];
+            let update_result = char_state
+                .event_probability_map
+                .update_weights(&weight_update);
+            match update_result {
+                Ok(_) => (),
+                Err(_) => warn!(
+                    target: LOG_CALAMITY,
+                    "could not change character {:?}'s death probability",
+                    CharacterID(id)
+                ),
+            }
+        }
+
+        Ok(())
+    }
+}
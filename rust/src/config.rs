@@ -0,0 +1,203 @@
+use crate::calamity::CalamityKind;
+use crate::map::MapKind;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// -- World config --
+
+// the knobs that control how big a world is and how its history plays out.
+// any field missing from a config file falls back to its default value.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub map: MapKind,                // the map generator used to lay out the cities
+    pub calamity: CalamityKind, // the first calamity, which drives everyone out of the start city
+    pub max_time: usize,        // the last time step of the history
+    pub num_layers: usize,      // number of layers of cities, including the start and end layers
+    pub min_cities_in_layer: usize, // fewest cities in each in between layer
+    pub max_cities_in_layer: usize, // most cities in each in between layer
+    pub num_characters: usize,  // number of characters to generate, before anyone is born
+    pub num_items: usize,       // number of items to generate
+    pub calamity_freq: usize, // when the calamity starts, and how long it takes to spread down a road of length 1
+    pub calamity_deadliness: usize, // calamity's kill probability increases with respect to this every time step
+    pub calamity_spread_chance: f64, // chance each time step of the calamity spreading down a road, once it's been in a city long enough
+    pub calamity_duration: usize, // how many time steps the calamity stays in a city before receding
+    pub outbreak_chance: f64, // chance each time step, once the first calamity has started, of another breaking out somewhere else
+    pub max_calamities: usize, // how many calamities can break out over the whole history, including the first
+    pub perception_range: usize, // how many roads away characters can tell where the calamity is
+    pub family_chance: f64,    // chance of each character starting out as kin to someone else
+    pub birth_chance: f64, // chance each time step of each grown character who's settled somewhere safe having a child
+    pub come_of_age: usize, // how many time steps old a character has to be to have children
+    pub lifespan: usize, // how many time steps characters live, give or take a quarter, if nothing else gets them first
+    pub illness_chance: f64, // chance each time step of a character at the end of their lifespan falling fatally ill, less for the young
+    pub accident_chance: f64, // chance each time step of a travelling character dying in an accident on the road
+    pub violence_chance: f64, // chance of rivals who meet coming to blows, and one of them dying
+    pub encounter_pow: u32, // encounter chance is determined by the city population to the power of this
+    pub prob_item_passed: f32, // chance of an item changing hands during an encounter with a living character
+    pub abandon_after: usize, // a city is abandoned once the calamity has been in it for this many time steps
+    pub ruin_after: usize,    // an abandoned city falls into ruin after this many more time steps
+    pub found_chance: f64, // chance each time step of a new city being founded ahead of the calamity
+    pub rename_chance: f64, // chance each time step of each city the calamity hasn't reached being renamed
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        WorldConfig {
+            map: MapKind::LayeredFunnel,
+            calamity: CalamityKind::Fire,
+            max_time: 9,
+            num_layers: 5,
+            min_cities_in_layer: 1,
+            max_cities_in_layer: 2,
+            num_characters: 6,
+            num_items: 6,
+            calamity_freq: 5,
+            calamity_deadliness: 2,
+            calamity_spread_chance: 0.5,
+            calamity_duration: 12,
+            outbreak_chance: 0.05,
+            max_calamities: 3,
+            perception_range: 2,
+            family_chance: 0.2,
+            birth_chance: 0.1,
+            come_of_age: 8,
+            lifespan: 60,
+            illness_chance: 0.02,
+            accident_chance: 0.01,
+            violence_chance: 0.1,
+            encounter_pow: 2,
+            prob_item_passed: 1.0,
+            abandon_after: 3,
+            ruin_after: 2,
+            found_chance: 0.1,
+            rename_chance: 0.02,
+        }
+    }
+}
+
+impl WorldConfig {
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        let config: WorldConfig = serde_json::from_str(json).map_err(ConfigError::Json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let config: WorldConfig = toml::from_str(toml).map_err(ConfigError::Toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // loads a config file, picking the format from the file extension (.json or .toml)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            Some("toml") => Self::from_toml(&contents),
+            _ => Err(ConfigError::UnknownFormat(path.display().to_string())),
+        }
+    }
+
+    // checks that a world can actually be generated from this config
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.num_layers < 2 {
+            return Err(ConfigError::Invalid(
+                "num_layers must be at least 2 (a start and an end layer)".to_string(),
+            ));
+        }
+        if self.min_cities_in_layer == 0 {
+            return Err(ConfigError::Invalid(
+                "min_cities_in_layer must be at least 1".to_string(),
+            ));
+        }
+        if self.min_cities_in_layer > self.max_cities_in_layer {
+            return Err(ConfigError::Invalid(format!(
+                "min_cities_in_layer ({}) is greater than max_cities_in_layer ({})",
+                self.min_cities_in_layer, self.max_cities_in_layer
+            )));
+        }
+        if self.num_characters == 0 {
+            return Err(ConfigError::Invalid(
+                "num_characters must be at least 1".to_string(),
+            ));
+        }
+        if self.calamity_freq == 0 {
+            return Err(ConfigError::Invalid(
+                "calamity_freq must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.prob_item_passed) {
+            return Err(ConfigError::Invalid(format!(
+                "prob_item_passed ({}) must be between 0 and 1",
+                self.prob_item_passed
+            )));
+        }
+        if self.calamity_duration == 0 {
+            return Err(ConfigError::Invalid(
+                "calamity_duration must be at least 1".to_string(),
+            ));
+        }
+        if self.max_calamities == 0 {
+            return Err(ConfigError::Invalid(
+                "max_calamities must be at least 1".to_string(),
+            ));
+        }
+        if self.lifespan == 0 {
+            return Err(ConfigError::Invalid(
+                "lifespan must be at least 1".to_string(),
+            ));
+        }
+        if self.abandon_after == 0 {
+            return Err(ConfigError::Invalid(
+                "abandon_after must be at least 1".to_string(),
+            ));
+        }
+        for (name, chance) in [
+            ("calamity_spread_chance", self.calamity_spread_chance),
+            ("outbreak_chance", self.outbreak_chance),
+            ("family_chance", self.family_chance),
+            ("birth_chance", self.birth_chance),
+            ("illness_chance", self.illness_chance),
+            ("accident_chance", self.accident_chance),
+            ("violence_chance", self.violence_chance),
+            ("found_chance", self.found_chance),
+            ("rename_chance", self.rename_chance),
+        ] {
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(ConfigError::Invalid(format!(
+                    "{} ({}) must be between 0 and 1",
+                    name, chance
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    UnknownFormat(String), // the path of a file that isn't .json or .toml
+    Invalid(String),       // why the config can't be used
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "could not read world config: {}", err),
+            ConfigError::Json(err) => write!(f, "could not parse world config json: {}", err),
+            ConfigError::Toml(err) => write!(f, "could not parse world config toml: {}", err),
+            ConfigError::UnknownFormat(path) => {
+                write!(f, "world config {} is not a .json or .toml file", path)
+            }
+            ConfigError::Invalid(reason) => write!(f, "invalid world config: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,129 @@
+use crate::config::ConfigError;
+use crate::world::{CharacterID, CityID, EventID, ItemID};
+use rand::distributions::WeightedError;
+use std::fmt;
+
+// -- World errors --
+
+// something that went wrong while generating a world or its history
+#[derive(Debug)]
+pub enum WorldError {
+    InvalidConfig(ConfigError),
+    MissingCity(CityID),
+    MissingCharacter(CharacterID),
+    MissingEvent(EventID),
+    MissingItem(ItemID),
+    NoStartCity,                    // the world has no cities for characters to start in
+    IncompleteRecord(&'static str), // an item record is missing the owner, location or event
+    Weights(WeightedError),         // a character's event probabilities couldn't be built
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorldError::InvalidConfig(err) => write!(f, "{}", err),
+            WorldError::MissingCity(CityID(id)) => write!(f, "no city with id {}", id),
+            WorldError::MissingCharacter(CharacterID(id)) => {
+                write!(f, "no character with id {}", id)
+            }
+            WorldError::MissingEvent(EventID(id)) => write!(f, "no event with id {}", id),
+            WorldError::MissingItem(ItemID(id)) => write!(f, "no item with id {}", id),
+            WorldError::NoStartCity => write!(f, "the world has no start city"),
+            WorldError::IncompleteRecord(missing) => {
+                write!(f, "item record has no associated {}", missing)
+            }
+            WorldError::Weights(err) => write!(f, "invalid event probabilities: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WorldError {}
+
+impl From<ConfigError> for WorldError {
+    fn from(err: ConfigError) -> Self {
+        WorldError::InvalidConfig(err)
+    }
+}
+
+impl From<WeightedError> for WorldError {
+    fn from(err: WeightedError) -> Self {
+        WorldError::Weights(err)
+    }
+}
+
+// -- Narration errors --
+
+// something that went wrong while turning a history into item descriptions and stories
+#[derive(Debug)]
+pub enum NarrationError {
+    Io(std::io::Error),         // the descriptions file couldn't be read
+    Json(serde_json::Error),    // the descriptions file couldn't be parsed
+    World(WorldError),          // the history refers to something that isn't in the world
+    MissingDescription(String), // descriptions.json has no description for this item type
+    MissingLines(&'static str), // descriptions.json has no lines of this kind to choose from
+    Format(strfmt::FmtError),   // a line couldn't be filled in
+    NoRecords(ItemID),          // the item has no history to tell
+    UnknownItemType(String),    // the item type name doesn't end in a subtype number
+}
+
+impl fmt::Display for NarrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NarrationError::Io(err) => write!(f, "could not read descriptions: {}", err),
+            NarrationError::Json(err) => write!(f, "could not parse descriptions: {}", err),
+            NarrationError::World(err) => write!(f, "{}", err),
+            NarrationError::MissingDescription(item_type) => {
+                write!(f, "no description for item type {}", item_type)
+            }
+            NarrationError::MissingLines(kind) => write!(f, "no {} to choose from", kind),
+            NarrationError::Format(err) => write!(f, "could not format line: {}", err),
+            NarrationError::NoRecords(ItemID(id)) => write!(f, "item {} has no records", id),
+            NarrationError::UnknownItemType(item_type) => {
+                write!(f, "unknown item type {}", item_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NarrationError {}
+
+impl From<WorldError> for NarrationError {
+    fn from(err: WorldError) -> Self {
+        NarrationError::World(err)
+    }
+}
+
+impl From<strfmt::FmtError> for NarrationError {
+    fn from(err: strfmt::FmtError) -> Self {
+        NarrationError::Format(err)
+    }
+}
+
+// -- Save errors --
+
+// something that went wrong while saving or loading a world
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),      // the save file couldn't be read or written
+    Json(serde_json::Error), // the save file couldn't be parsed
+    UnsupportedVersion(u32), // the save was written by a newer version of the game
+    Invalid(&'static str),   // the file isn't a save, or is missing something a migration needs
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access save file: {}", err),
+            SaveError::Json(err) => write!(f, "could not parse save file: {}", err),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "save file version {} is newer than the supported version {}",
+                version,
+                crate::save::SAVE_VERSION
+            ),
+            SaveError::Invalid(reason) => write!(f, "invalid save file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
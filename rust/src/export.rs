@@ -0,0 +1,315 @@
+use crate::error::WorldError;
+use crate::graph::{Road, RoadDirection};
+use crate::world::{City, CityID, ItemID, Position, World};
+use std::fmt::Write;
+
+// -- Colours --
+
+const NO_CALAMITY_COLOUR: &str = "#ffffff";
+const EARLY_CALAMITY_COLOUR: (u8, u8, u8) = (214, 48, 39); // the calamity got there first
+const LATE_CALAMITY_COLOUR: (u8, u8, u8) = (254, 224, 144); // the calamity got there last
+const JOURNEY_COLOUR: &str = "#2c7bb6";
+
+// cities are coloured by when the calamity reached them, from red for the earliest to yellow for the latest
+fn calamity_colour(world: &World, city: &City) -> String {
+    let arrived = match city.calamity_arrived {
+        Some(arrived) => arrived,
+        None => return NO_CALAMITY_COLOUR.to_string(),
+    };
+    let fraction = arrived as f32 / world.config.max_time.max(1) as f32;
+    let mix = |early: u8, late: u8| {
+        (early as f32 + (late as f32 - early as f32) * fraction.min(1.0)).round() as u8
+    };
+    let (early, late) = (EARLY_CALAMITY_COLOUR, LATE_CALAMITY_COLOUR);
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix(early.0, late.0),
+        mix(early.1, late.1),
+        mix(early.2, late.2)
+    )
+}
+
+// e.g. "toolton (#5), calamity arrived at 5, abandoned at 7"
+fn city_label(city_id: CityID, city: &City) -> String {
+    let mut label = format!("{} (#{})", city.name, city_id.0);
+    if let Some(arrived) = city.calamity_arrived {
+        let _ = write!(label, ", calamity arrived at {}", arrived);
+    }
+    if let Some(receded) = city.calamity_receded {
+        let _ = write!(label, ", receded at {}", receded);
+    }
+    if let Some(abandoned) = city.abandoned {
+        let _ = write!(label, ", abandoned at {}", abandoned);
+    }
+    label
+}
+
+// the cities an item was in, in order, leaving out the times it changed hands without moving.
+// each stop is the city and when the item got there
+fn item_stops(world: &World, item_id: ItemID) -> Result<Vec<(CityID, usize)>, WorldError> {
+    let item = world
+        .items
+        .get(&item_id)
+        .ok_or(WorldError::MissingItem(item_id))?;
+    let mut stops: Vec<(CityID, usize)> = Vec::new();
+    for record in &item.owner_records {
+        let city_id = record.location()?;
+        if stops.last().map(|&(last_city, _)| last_city) != Some(city_id) {
+            stops.push((city_id, record.time));
+        }
+    }
+    Ok(stops)
+}
+
+// -- DOT --
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn dot_node(city_id: CityID) -> String {
+    format!("city{}", city_id.0)
+}
+
+// the cities, one rank per layer, and the roads between them
+fn write_dot_map(dot: &mut String, world: &World) -> Result<(), WorldError> {
+    for (layer_i, layer) in world.roads.layers().iter().enumerate() {
+        let _ = writeln!(dot, "    subgraph layer{} {{", layer_i);
+        let _ = writeln!(dot, "        rank = same;");
+        for &city_id in layer {
+            let city = world
+                .cities
+                .get(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?;
+            // ruins are drawn with a dashed outline
+            let style = if city.ruined.is_some() {
+                "filled,dashed"
+            } else {
+                "filled"
+            };
+            let _ = writeln!(
+                dot,
+                "        {} [label=\"{}\", style=\"{}\", fillcolor=\"{}\"];",
+                dot_node(city_id),
+                dot_escape(&city_label(city_id, city)),
+                style,
+                calamity_colour(world, city)
+            );
+        }
+        let _ = writeln!(dot, "    }}");
+    }
+
+    for road in world.roads.roads() {
+        let direction = match road.direction {
+            RoadDirection::OneWay => "forward",
+            RoadDirection::TwoWay => "both",
+        };
+        let _ = writeln!(
+            dot,
+            "    {} -> {} [dir={}, label=\"{}\"];",
+            dot_node(road.from),
+            dot_node(road.to),
+            direction,
+            road.length
+        );
+    }
+
+    Ok(())
+}
+
+// the world's cities and roads as a GraphViz graph. render with e.g. `dot -Tpng map.dot -o map.png`
+pub fn world_to_dot(world: &World) -> Result<String, WorldError> {
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph world {{");
+    let _ = writeln!(dot, "    rankdir = LR;");
+    let _ = writeln!(dot, "    node [shape=box];");
+    write_dot_map(&mut dot, world)?;
+    let _ = writeln!(dot, "}}");
+    Ok(dot)
+}
+
+// the world's map with the journey an item took drawn over it, each step labelled with when the item arrived
+pub fn item_to_dot(world: &World, item_id: ItemID) -> Result<String, WorldError> {
+    let stops = item_stops(world, item_id)?;
+
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph item{} {{", item_id.0);
+    let _ = writeln!(dot, "    rankdir = LR;");
+    let _ = writeln!(dot, "    node [shape=box];");
+    let _ = writeln!(dot, "    edge [color=\"#bbbbbb\", fontcolor=\"#bbbbbb\"];");
+    write_dot_map(&mut dot, world)?;
+    for pair in stops.windows(2) {
+        let ((from, _), (to, arrived)) = (pair[0], pair[1]);
+        let _ = writeln!(
+            dot,
+            "    {} -> {} [color=\"{}\", fontcolor=\"{}\", penwidth=2, constraint=false, label=\"{}\"];",
+            dot_node(from),
+            dot_node(to),
+            JOURNEY_COLOUR,
+            JOURNEY_COLOUR,
+            arrived
+        );
+    }
+    let _ = writeln!(dot, "}}");
+    Ok(dot)
+}
+
+// -- SVG --
+
+const SVG_MARGIN: f32 = 60.0;
+const CITY_RADIUS: f32 = 10.0;
+
+fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the smallest box around every city, with room for labels
+fn svg_view_box(world: &World) -> (f32, f32, f32, f32) {
+    let positions: Vec<Position> = world.cities.values().map(|city| city.position).collect();
+    let min_x = positions.iter().map(|p| p.x).fold(0.0, f32::min) - SVG_MARGIN;
+    let min_y = positions.iter().map(|p| p.y).fold(0.0, f32::min) - SVG_MARGIN;
+    let max_x = positions.iter().map(|p| p.x).fold(0.0, f32::max) + SVG_MARGIN;
+    let max_y = positions.iter().map(|p| p.y).fold(0.0, f32::max) + SVG_MARGIN;
+    (min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+fn svg_road(svg: &mut String, world: &World, road: &Road) -> Result<(), WorldError> {
+    let from = world
+        .cities
+        .get(&road.from)
+        .ok_or(WorldError::MissingCity(road.from))?
+        .position;
+    let to = world
+        .cities
+        .get(&road.to)
+        .ok_or(WorldError::MissingCity(road.to))?
+        .position;
+    let marker = match road.direction {
+        RoadDirection::OneWay => " marker-end=\"url(#arrow)\"",
+        RoadDirection::TwoWay => "",
+    };
+    let _ = writeln!(
+        svg,
+        "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#999999\" stroke-width=\"2\"{}/>",
+        from.x, from.y, to.x, to.y, marker
+    );
+    Ok(())
+}
+
+fn svg_city(svg: &mut String, world: &World, city_id: CityID, city: &City) {
+    let position = city.position;
+    let dash = if city.ruined.is_some() {
+        " stroke-dasharray=\"3 2\""
+    } else {
+        ""
+    };
+    // hovering over a city shows everything the label leaves out
+    let _ = writeln!(
+        svg,
+        "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"#333333\"{}><title>{}</title></circle>",
+        position.x,
+        position.y,
+        CITY_RADIUS,
+        calamity_colour(world, city),
+        dash,
+        svg_escape(&city_label(city_id, city))
+    );
+    let _ = writeln!(
+        svg,
+        "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"11\">{}</text>",
+        position.x,
+        position.y + CITY_RADIUS * 2.2,
+        svg_escape(&city.name)
+    );
+}
+
+fn write_svg_map(svg: &mut String, world: &World) -> Result<(), WorldError> {
+    let (x, y, width, height) = svg_view_box(world);
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        x, y, width, height, width, height
+    );
+    let _ = writeln!(
+        svg,
+        "  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"{}\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#999999\"/></marker></defs>",
+        10.0 + CITY_RADIUS
+    );
+    let _ = writeln!(
+        svg,
+        "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#fafafa\"/>",
+        x, y, width, height
+    );
+    for road in world.roads.roads() {
+        svg_road(svg, world, road)?;
+    }
+    for (&city_id, city) in &world.cities {
+        svg_city(svg, world, city_id, city);
+    }
+    Ok(())
+}
+
+// the world's map as a standalone svg, with the cities where they are in the game
+pub fn world_to_svg(world: &World) -> Result<String, WorldError> {
+    let mut svg = String::new();
+    write_svg_map(&mut svg, world)?;
+    let _ = writeln!(svg, "</svg>");
+    Ok(svg)
+}
+
+// the world's map with the journey an item took drawn over it, one line for each step from city to city
+pub fn item_to_svg(world: &World, item_id: ItemID) -> Result<String, WorldError> {
+    let stops = item_stops(world, item_id)?;
+    let item = world
+        .items
+        .get(&item_id)
+        .ok_or(WorldError::MissingItem(item_id))?;
+
+    let mut svg = String::new();
+    write_svg_map(&mut svg, world)?;
+
+    for pair in stops.windows(2) {
+        let ((from, _), (to, arrived)) = (pair[0], pair[1]);
+        let mut points: Vec<String> = Vec::new();
+        for city_id in [from, to] {
+            let position = world
+                .cities
+                .get(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?
+                .position;
+            points.push(format!("{},{}", position.x, position.y));
+        }
+        let _ = writeln!(
+            svg,
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"3\" stroke-opacity=\"0.8\"><title>#{} {}, arrived at {}</title></polyline>",
+            points.join(" "),
+            JOURNEY_COLOUR,
+            item_id.0,
+            svg_escape(&item.item_type.to_string()),
+            arrived
+        );
+    }
+
+    // when the item got to each city
+    for &(city_id, arrived) in &stops {
+        let position = world
+            .cities
+            .get(&city_id)
+            .ok_or(WorldError::MissingCity(city_id))?
+            .position;
+        let _ = writeln!(
+            svg,
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"10\" fill=\"{}\">{}</text>",
+            position.x,
+            position.y - CITY_RADIUS * 1.5,
+            JOURNEY_COLOUR,
+            arrived
+        );
+    }
+
+    let _ = writeln!(svg, "</svg>");
+    Ok(svg)
+}
@@ -1,430 +1,271 @@
-extern crate strfmt;
-use std::collections::HashMap;
-
+use crate::config::{ConfigError, WorldConfig};
+use crate::error::WorldError;
+use crate::narration::*;
+use crate::save::{load_world, save_world};
 use crate::world::*;
-use godot::classes::Node;
+use godot::classes::{Node, ProjectSettings};
 use godot::prelude::*;
-use rand::seq::SliceRandom;
-use rand::Rng;
-use serde::{Deserialize, Serialize};
-use strfmt::strfmt;
+use log::{debug, info, Level, LevelFilter, Log, Metadata, Record};
+use rand::{thread_rng, Rng};
 
-use std::fs::File;
-use stringcase::Caser;
+use std::error::Error;
+use std::path::Path;
 
-const MAX_WEAR_DESC: usize = 2;
+const WORLD_CONFIG_PATH: &str = "writing/world_config.toml";
+const DESCRIPTIONS_PATH: &str = "writing/descriptions.json";
 
-#[derive(Serialize, Deserialize)]
-struct WearDescs {
-    bricabrac_wear: Vec<Vec<String>>,
-    accessory_wear: Vec<Vec<String>>,
-}
+struct MyExtension;
 
-#[derive(Serialize, Deserialize)]
-struct EventLines {
-    creation_lines: Vec<Vec<String>>,
-    death_lines: Vec<Vec<String>>,
-    move_lines: Vec<Vec<String>>,
-    exchange_lines: Vec<Vec<String>>,
-    postmortem_exchange_lines: Vec<Vec<String>>,
-    _format_rules: Vec<String>,
+#[gdextension]
+unsafe impl ExtensionLibrary for MyExtension {
+    fn on_level_init(level: InitLevel) {
+        if level == InitLevel::Scene {
+            GodotLogger::install();
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct DescJson {
-    initial_descriptions: HashMap<String, String>,
-    wear_descriptions: WearDescs,
-    story_intros: Vec<String>,
-    story_outros: Vec<String>,
-    event_lines: EventLines,
+// forwards log records from world generation and narration to godot's output
+struct GodotLogger;
+
+static GODOT_LOGGER: GodotLogger = GodotLogger;
+
+impl GodotLogger {
+    fn install() {
+        // this only fails if a logger is already installed, e.g. after the extension is reloaded
+        if log::set_logger(&GODOT_LOGGER).is_ok() {
+            // generation is very chatty, so release builds only show warnings and errors
+            log::set_max_level(if cfg!(debug_assertions) {
+                LevelFilter::Debug
+            } else {
+                LevelFilter::Warn
+            });
+        }
+    }
 }
 
-struct MyExtension;
+impl Log for GodotLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
 
-#[gdextension]
-unsafe impl ExtensionLibrary for MyExtension {}
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => godot_error!("[{}] {}", record.target(), record.args()),
+            Level::Warn => godot_warn!("[{}] {}", record.target(), record.args()),
+            _ => godot_print!("[{}] {}", record.target(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 #[derive(GodotClass)]
 #[class(base=Node)]
 struct History {
+    // the seed to generate the world from. a negative seed picks a new random seed every time.
+    #[export]
+    seed: i64,
+    // the seed the current world was actually generated from, so a history can be reproduced
+    #[var(get)]
+    generated_seed: i64,
+    // generation knobs. these start out at the WorldConfig defaults and take precedence over the config file.
+    #[export(range = (1.0, 50.0, or_greater))]
+    num_characters: i64,
+    #[export(range = (1.0, 50.0, or_greater))]
+    num_items: i64,
+    #[export(range = (2.0, 20.0, or_greater))]
+    num_layers: i64,
+    #[export(range = (0.0, 100.0, or_greater))]
+    max_time: i64,
+    #[export(range = (1.0, 20.0, or_greater))]
+    calamity_freq: i64,
+    #[export(range = (0.0, 10.0, or_greater))]
+    calamity_deadliness: i64,
     world: World,
     generated: bool,
     items: Array<Gd<ItemData>>,
     base: Base<Node>,
 }
 
-fn get_item_types(item: &Item) -> (usize, usize, usize) {
-    let item_type_string = item.item_type.to_string();
-    let item_supertype_i: usize = match item.item_type {
-        // bricabrac
-        ItemType::Teapot1
-        | ItemType::Teapot2
-        | ItemType::Teapot3
-        | ItemType::Vase1
-        | ItemType::Vase2
-        | ItemType::Vase3
-        | ItemType::Cup1
-        | ItemType::Statue
-        | ItemType::Orb => 0,
-        // accessories
-        ItemType::Belt
-        | ItemType::Bracelet
-        | ItemType::Hat
-        | ItemType::Shoes1
-        | ItemType::Shoes2
-        | ItemType::Shoes3
-        | ItemType::Sunglasses
-        | ItemType::Necklace => 1,
-    };
-    let item_type_i: usize = match item.item_type {
-        ItemType::Teapot1 | ItemType::Teapot2 | ItemType::Teapot3 => 0,
-        ItemType::Vase1 | ItemType::Vase2 | ItemType::Vase3 => 1,
-        ItemType::Cup1 => 2,
-        ItemType::Statue => 3,
-        ItemType::Orb => unreachable!(),
-        ItemType::Belt => 5,
-        ItemType::Bracelet => 6,
-        ItemType::Hat => 7,
-        ItemType::Shoes1 | ItemType::Shoes2 => 8,
-        ItemType::Shoes3 => unreachable!(),
-        ItemType::Sunglasses => 9,
-        ItemType::Necklace => 10,
-    };
-    let mut item_subtype_i: usize = item_type_string
-        .chars()
-        .last()
-        .unwrap()
-        .to_string()
-        .parse()
-        .unwrap();
-    item_subtype_i -= 1;
-
-    (item_supertype_i, item_type_i, item_subtype_i)
-}
-
-pub fn generate_description(
-    item: &Item,
-    item_types: (usize, usize, usize),
-    descs: &DescJson,
-) -> Array<GString> {
-    let mut rng = rand::thread_rng();
-    let wear_desc_amt = rng.gen_range(0..=MAX_WEAR_DESC);
-    let mut description: Array<GString> = Array::new();
-
-    let first_desc: GString = descs.initial_descriptions[&item.item_type.to_string()]
-        .to_string()
-        .into();
-    description.push(first_desc);
-
-     //desc of wear
-    let wear_descs = &descs.wear_descriptions;
-    let mut wear_format_hashmap: HashMap<String, String> = HashMap::new();
-    
-    let wear_list = match item_types.0 {
-        0 => &wear_descs.bricabrac_wear,
-        1 => &wear_descs.accessory_wear,
-        _ => unreachable!(),
-    };
-    
-    if item_types.0 == 0 {
-        let mut i: usize = 0;
-        for desc in wear_list
-            .get(item_types.1 + 1)
-            .expect("the appropriate non-formatted wear description")
-            .iter()
-        {
-            wear_format_hashmap.insert(i.to_string(), desc.to_string());
-            i += 1;
-        }
+impl History {
+    // builds the world config from the config file (if there is one) and the exported properties
+    fn world_config(&self) -> Result<WorldConfig, ConfigError> {
+        let mut config = if Path::new(WORLD_CONFIG_PATH).exists() {
+            WorldConfig::load(WORLD_CONFIG_PATH)?
+        } else {
+            WorldConfig::default()
+        };
+        config.num_characters = self.num_characters.max(0) as usize;
+        config.num_items = self.num_items.max(0) as usize;
+        config.num_layers = self.num_layers.max(0) as usize;
+        config.max_time = self.max_time.max(0) as usize;
+        config.calamity_freq = self.calamity_freq.max(0) as usize;
+        config.calamity_deadliness = self.calamity_deadliness.max(0) as usize;
+        config.validate()?;
+        Ok(config)
     }
 
+    fn try_regenerate(&mut self) -> Result<(), Box<dyn Error>> {
+        // generate world
+        let seed = if self.seed < 0 {
+            thread_rng().gen_range(0..i64::MAX)
+        } else {
+            self.seed
+        };
+        let config = self.world_config()?;
+        info!(target: LOG_WORLDGEN, "generating world with seed {}...", seed);
+        let mut world = World::generate_world_with_seed(config, seed as u64)?;
 
+        // generate events
+        world.generate_events()?;
 
-    
-    let wear_desc: Vec<GString> = wear_list[0]
-        .choose_multiple(&mut rng, wear_desc_amt)
-        .map(|desc| {
-            GString::from(
-                strfmt(desc, &wear_format_hashmap)
-                    .expect("an appropriate wear description that's been formatted"),
-            )
-        })
-        .collect();
-    description.extend(wear_desc);
+        // log item events for debugging
+        for (item_id, item) in &world.items {
+            debug!(
+                target: LOG_NARRATION,
+                "item #{:?} (of type {:?})'s events:",
+                item_id,
+                item.item_type
+            );
 
-    description
-}
+            for record in &item.owner_records {
+                if let Some(event_id) = record.event {
+                    let event = world
+                        .events
+                        .get(&event_id)
+                        .ok_or(WorldError::MissingEvent(event_id))?;
+                    debug!(target: LOG_NARRATION, "event #{:?}: {:?}", event_id, event.summary);
+                }
+            }
+        }
 
-pub fn get_records_from_time(records: &Vec<ItemMoveRecord>, time: usize) -> Vec<&ItemMoveRecord> {
-    let result: Vec<&ItemMoveRecord> = records
-        .into_iter()
-        .filter(|&record| record.time == time)
-        .collect();
+        self.set_world(world)
+    }
 
-    result
-}
+    // narrates a generated (or loaded) world and makes it the current history
+    fn set_world(&mut self, world: World) -> Result<(), Box<dyn Error>> {
+        // generate item data for each item
+        let descs = load_descriptions(DESCRIPTIONS_PATH)?;
+        let mut item_data: Array<Gd<ItemData>> = Array::new();
+        for narration in narrate_world(&world, &descs)? {
+            item_data.push(ItemData::from_narration(narration, &world)?);
+        }
 
-pub fn format_event_lines(
-    lines: &Vec<String>,
-    world: &World,
-    record: &ItemMoveRecord,
-) -> Array<GString> {
-    let event = world
-        .events
-        .get(&record.expect_event())
-        .expect("the event obj associated with the given record");
-    let mut lines_gstring: Array<GString> = Array::new();
-
-    // get format parameters ready
-    let mut format_vars: HashMap<String, String> = HashMap::new();
-    // insert owner name
-    let owner = world
-        .characters
-        .get(&record.expect_owner())
-        .expect("reference to the character obj of the owner of the item with the given record");
-    format_vars.insert(
-        "owner_name".to_string(),
-        owner.name.clone().to_pascal_case(),
-    );
-    // insert city name
-    let city = world
-        .cities
-        .get(&record.expect_location())
-        .expect("reference to the city obj of the given record");
-    format_vars.insert("city_name".to_string(), city.name.clone().to_pascal_case());
-    // insert pronouns of owner
-    format_vars.insert(
-        "nominative_pronoun".to_string(),
-        owner.pronouns.nominative.clone(),
-    );
-    format_vars.insert(
-        "accusative_pronoun".to_string(),
-        owner.pronouns.accusative.clone(),
-    );
-    format_vars.insert(
-        "dep_genitive_pronoun".to_string(),
-        owner.pronouns.dep_genitive.clone(),
-    );
-    // add old owner info if applicable.
-    if event.event_type == EventType::EventEncounter {
-        let old_owner = world
-            .characters
-            .get(&event.characters[1])
-            .expect("reference to the character object of the old owner of the item associated with the given record");
-        format_vars.insert(
-            "old_owner_name".to_string(),
-            old_owner.name.clone().to_pascal_case(),
-        );
-        format_vars.insert(
-            "nominative_pronoun1".to_string(),
-            old_owner.pronouns.nominative.clone(),
-        );
-        format_vars.insert(
-            "accusative_pronoun1".to_string(),
-            old_owner.pronouns.accusative.clone(),
-        );
-        format_vars.insert(
-            "dep_genitive_pronoun1".to_string(),
-            old_owner.pronouns.dep_genitive.clone(),
-        );
-    }
-    // add old city info if applicable.
-    if event.event_type == EventType::EventMove {
-        // TODO
-    }
-    // add year
-    format_vars.insert("year".to_string(), event.start_time.to_string());
-    // format all lines
-    for line in lines {
-        // format line
-        let line_formatted = strfmt(line, &format_vars)
-            .expect("one of the formatted lines of the story generated for this event");
-        println!("Creation event line: {:?}", line_formatted);
-        lines_gstring.push(&line_formatted.into());
+        // only replace the current history once the new one has been generated successfully
+        self.generated_seed = world.seed as i64;
+        self.world = world;
+        self.items = item_data;
+        self.generated = true;
+
+        Ok(())
     }
 
-    // return formatted lines
-    lines_gstring
-}
+    fn try_load_from(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        let world = load_world(path)?;
+        info!(target: LOG_WORLDGEN, "loaded world with seed {} from {}", world.seed, path);
 
-pub fn generate_lines_from_event(
-    world: &World,
-    record: &ItemMoveRecord,
-    descs: &DescJson,
-) -> Option<Array<GString>> {
-    let event = world
-        .events
-        .get(
-            &record
-                .event
-                .expect("the event id of the event associated with the given record"),
-        )
-        .expect("the event obj of the event associated with the given record");
-    match event.event_type {
-        EventType::EventCreation(_) => {
-            let lines = descs
-                .event_lines
-                .creation_lines
-                .choose(&mut rand::thread_rng())
-                .expect("randomly chosen creation line");
-            Some(format_event_lines(lines, world, record))
-        }
-        EventType::EventDeath => {
-            let lines = descs
-                .event_lines
-                .death_lines
-                .choose(&mut rand::thread_rng())
-                .expect("randomly chosen death line");
-            Some(format_event_lines(lines, world, record))
-        }
-        EventType::EventMove => {
-            let lines = descs
-                .event_lines
-                .move_lines
-                .choose(&mut rand::thread_rng())
-                .expect("randomly chosen move line");
-            Some(format_event_lines(lines, world, record))
-        }
-        EventType::EventEncounter => {
-            let encountered_id = event
-                .characters
-                .get(1)
-                .expect("the id of the character that is encountered");
-            let is_postmortem_encounter = world
-                .characters
-                .get(encountered_id)
-                .expect("the character struct for the encountered character")
-                .has_died(world);
-            let const_lines = if is_postmortem_encounter {
-                &descs.event_lines.postmortem_exchange_lines
-            } else {
-                &descs.event_lines.exchange_lines
-            };
-            let lines = const_lines
-                .choose(&mut rand::thread_rng())
-                .expect("randomly chosen exchange line");
-            Some(format_event_lines(lines, world, record))
-        }
-        _ => None,
-    }
-}
+        // show the loaded world's settings, so regenerating starts from the same place
+        let config = world.config.clone();
+        self.num_characters = config.num_characters as i64;
+        self.num_items = config.num_items as i64;
+        self.num_layers = config.num_layers as i64;
+        self.max_time = config.max_time as i64;
+        self.calamity_freq = config.calamity_freq as i64;
+        self.calamity_deadliness = config.calamity_deadliness as i64;
 
-pub fn generate_stories(world: &World, item: &Item, descs: &DescJson) -> Array<Gd<ItemStory>> {
-    let mut stories: Array<Gd<ItemStory>> = Array::new();
-    let records = &item.owner_records;
-    let oldest_records = get_records_from_time(&records, 0);
-    println!("oldest records: {:?}", oldest_records);
-    let last_time_seen = records
-        .last()
-        .expect("the last record in the owner records of this item")
-        .time;
-    let newest_records = get_records_from_time(&records, last_time_seen);
-
-    // generate oldest story, special dialogue for this
-    let mut oldest_story_lines: Array<GString> = Array::new();
-    // add lines for event
-    oldest_story_lines.extend_array(
-        &generate_lines_from_event(
-            world,
-            oldest_records
-                .first()
-                .expect("oldest record pertaining to this item"),
-            descs,
-        )
-        .expect("lines generated for the oldest record associated with the given item"),
-    );
-    // choose an outro
-    let outro = descs
-        .story_outros
-        .choose(&mut rand::thread_rng())
-        .expect("randomly chosen story outro");
-    oldest_story_lines.push(&outro.into());
-    // push to array of stories
-    stories.push(ItemStory::new(oldest_story_lines));
-
-    // generate in between stories
-    for record_i in 1..(records.len() - 1) {
-        let record = &records[record_i];
-        let lines_option = generate_lines_from_event(world, &record, descs);
-        match lines_option {
-            Some(lines) => stories.push(ItemStory::new(lines)),
-            None => (),
-        }
+        self.set_world(world)
     }
-
-    // generate newest story, special dialogue for this
-    let mut newest_story_lines: Array<GString> = Array::new();
-    // choose an intro
-    let intro = descs
-        .story_intros
-        .choose(&mut rand::thread_rng())
-        .expect("randomly chosen story intro");
-    newest_story_lines.push(&intro.into());
-    // add lines for event
-    newest_story_lines.extend_array(
-        &generate_lines_from_event(
-            world,
-            newest_records
-                .last()
-                .expect("newest record associated with the given item"),
-            &descs,
-        )
-        .expect("lines generated for the newest record associated with the given ite"),
-    );
-    // push to array of stories
-    stories.push(ItemStory::new(newest_story_lines));
-
-    // collect into array and return
-    stories
 }
 
 #[godot_api]
 impl History {
+    // emitted instead of crashing the game when a history can't be generated
+    #[signal]
+    fn generation_failed(message: GString);
+
+    // generates the history if it hasn't been generated yet
     #[func]
     fn generate_history(&mut self) {
-        // generate events
-        godot_print!("Generating events...");
-        self.world.generate_events();
-        godot_print!("Done generating events");
-
-        // print item events for debugging
-        let world_items = &self.world.items;
-        godot_print!("\nItem Event Display:");
-        for (item_id, item) in world_items {
-            godot_print!("-------------------------");
-            godot_print!(
-                "Item #{:?} (of type {:?})'s events:",
-                item_id,
-                item.item_type
-            );
+        if !self.generated {
+            self.regenerate();
+        }
+    }
 
-            for record in &item.owner_records {
-                match record.event {
-                    Some(event_id) => {
-                        let &ref event = self.world.events.get(&event_id).unwrap();
-                        godot_print!("event #{:?}: {:?},", event_id, event.summary);
-                    }
-                    _ => (),
-                }
-            }
+    // throws away the current history and generates a new one from the exported properties
+    #[func]
+    fn regenerate(&mut self) {
+        if let Err(err) = self.try_regenerate() {
+            let message = err.to_string();
+            godot_error!("Could not generate history: {}", message);
+            self.base_mut()
+                .emit_signal("generation_failed", &[GString::from(message).to_variant()]);
         }
+    }
 
-        // generate item data for each item
-        let desc_file = File::open("writing/descriptions.json").expect("opening descriptions file");
-        let descs: DescJson = serde_json::from_reader(desc_file).unwrap();
-        let mut item_data: Array<Gd<ItemData>> = Array::new();
-        for (_, item) in world_items.into_iter() {
-            let item_types = get_item_types(item);
-            let item_type_string = item.item_type.to_string();
-            let description = generate_description(item, item_types, &descs);
-            let stories = generate_stories(&self.world, item, &descs);
+    // saves the current history, e.g. to "user://history.json". returns false if it couldn't be saved.
+    #[func]
+    fn save_to(&self, path: GString) -> bool {
+        if !self.generated {
+            godot_error!("Could not save history: no history has been generated");
+            return false;
+        }
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        match save_world(&self.world, &path) {
+            Ok(()) => true,
+            Err(err) => {
+                godot_error!("Could not save history to {}: {}", path, err);
+                false
+            }
+        }
+    }
 
-            item_data.push(ItemData::new(item_type_string.into(), description, stories));
+    // replaces the current history with one saved by save_to. returns false if it couldn't be loaded.
+    #[func]
+    fn load_from(&mut self, path: GString) -> bool {
+        let path = ProjectSettings::singleton()
+            .globalize_path(&path)
+            .to_string();
+        match self.try_load_from(&path) {
+            Ok(()) => true,
+            Err(err) => {
+                godot_error!("Could not load history from {}: {}", path, err);
+                false
+            }
         }
+    }
 
-        self.items = item_data;
+    // the map position of every city, by city id
+    #[func]
+    fn get_city_positions(&self) -> Dictionary {
+        let mut positions = Dictionary::new();
+        for (city_id, city) in &self.world.cities {
+            positions.set(city_id.0 as i64, to_vector(city.position));
+        }
+        positions
+    }
 
-        self.generated = true;
+    // every road on the map, as a line from one city's position to the other's
+    #[func]
+    fn get_roads(&self) -> Array<PackedVector2Array> {
+        let mut roads = Array::new();
+        for road in self.world.roads.roads() {
+            if let (Some(from), Some(to)) = (
+                self.world.cities.get(&road.from),
+                self.world.cities.get(&road.to),
+            ) {
+                let line: PackedVector2Array = [to_vector(from.position), to_vector(to.position)]
+                    .into_iter()
+                    .collect();
+                roads.push(&line);
+            }
+        }
+        roads
     }
 
     #[func]
@@ -441,8 +282,17 @@ impl History {
 #[godot_api]
 impl INode for History {
     fn init(base: Base<Node>) -> Self {
+        let config = WorldConfig::default();
         Self {
-            world: World::generate_world(),
+            seed: -1,
+            generated_seed: -1,
+            num_characters: config.num_characters as i64,
+            num_items: config.num_items as i64,
+            num_layers: config.num_layers as i64,
+            max_time: config.max_time as i64,
+            calamity_freq: config.calamity_freq as i64,
+            calamity_deadliness: config.calamity_deadliness as i64,
+            world: World::new(config),
             generated: false,
             items: array![],
             base,
@@ -450,6 +300,10 @@ impl INode for History {
     }
 }
 
+fn to_vector(position: Position) -> Vector2 {
+    Vector2::new(position.x, position.y)
+}
+
 #[derive(GodotClass)]
 #[class(no_init)]
 struct ItemData {
@@ -459,6 +313,9 @@ struct ItemData {
     description: Array<GString>,
     #[var]
     stories: Array<Gd<ItemStory>>,
+    // the map positions of the cities the item has been to, in order
+    #[var]
+    travels: PackedVector2Array,
 }
 
 #[godot_api]
@@ -467,13 +324,50 @@ impl ItemData {
         item_type: GString,
         description: Array<GString>,
         stories: Array<Gd<ItemStory>>,
+        travels: PackedVector2Array,
     ) -> Gd<Self> {
         Gd::from_object(Self {
             item_type,
             description,
             stories,
+            travels,
         })
     }
+
+    fn from_narration(narration: ItemNarration, world: &World) -> Result<Gd<Self>, WorldError> {
+        let description = narration.description.iter().map(GString::from).collect();
+        let stories = narration
+            .stories
+            .iter()
+            .map(|lines| ItemStory::new(lines.iter().map(GString::from).collect()))
+            .collect();
+
+        let item = world
+            .items
+            .get(&narration.item_id)
+            .ok_or(WorldError::MissingItem(narration.item_id))?;
+        let mut travels = PackedVector2Array::new();
+        let mut last_city = None;
+        for record in &item.owner_records {
+            let city_id = record.location()?;
+            // exchanges don't move the item, so only add a point when it changes city
+            if last_city != Some(city_id) {
+                let city = world
+                    .cities
+                    .get(&city_id)
+                    .ok_or(WorldError::MissingCity(city_id))?;
+                travels.push(to_vector(city.position));
+                last_city = Some(city_id);
+            }
+        }
+
+        Ok(Self::new(
+            narration.item_type.into(),
+            description,
+            stories,
+            travels,
+        ))
+    }
 }
 
 #[derive(GodotClass)]
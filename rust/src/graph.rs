@@ -0,0 +1,235 @@
+use crate::world::CityID;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+
+// -- Roads --
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoadDirection {
+    OneWay, // can only be travelled from `from` to `to`
+    TwoWay, // can be travelled in both directions
+}
+
+// a road between two cities
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Road {
+    pub from: CityID,
+    pub to: CityID,
+    pub direction: RoadDirection,
+    pub length: usize, // how far apart the two cities are
+}
+
+impl Road {
+    // the city at the other end of the road, if the road can be taken from this city
+    pub fn leads_from(&self, city: CityID) -> Option<CityID> {
+        if self.from == city {
+            Some(self.to)
+        } else if self.to == city && self.direction == RoadDirection::TwoWay {
+            Some(self.from)
+        } else {
+            None
+        }
+    }
+
+    // the city at the other end of the road, if the road can be taken to this city
+    pub fn leads_to(&self, city: CityID) -> Option<CityID> {
+        if self.to == city {
+            Some(self.from)
+        } else if self.from == city && self.direction == RoadDirection::TwoWay {
+            Some(self.to)
+        } else {
+            None
+        }
+    }
+}
+
+// -- Road graph --
+
+// the cities of a world, which layer each of them is in, and the roads between them.
+// layer 0 holds the start city, where the calamity begins, and the last layer holds the end city.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RoadGraph {
+    layers: Vec<Vec<CityID>>,
+    roads: Vec<Road>,
+}
+
+impl RoadGraph {
+    pub fn new(num_layers: usize) -> Self {
+        RoadGraph {
+            layers: vec![Vec::new(); num_layers],
+            roads: Vec::new(),
+        }
+    }
+
+    pub fn add_city(&mut self, city: CityID, layer: usize) {
+        if self.layers.len() <= layer {
+            self.layers.resize(layer + 1, Vec::new());
+        }
+        self.layers[layer].push(city);
+    }
+
+    pub fn add_road(&mut self, from: CityID, to: CityID, direction: RoadDirection, length: usize) {
+        self.roads.push(Road {
+            from,
+            to,
+            direction,
+            length,
+        });
+    }
+
+    pub fn layers(&self) -> &Vec<Vec<CityID>> {
+        &self.layers
+    }
+
+    pub fn layer(&self, layer: usize) -> &[CityID] {
+        self.layers
+            .get(layer)
+            .map_or(&[], |cities| cities.as_slice())
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn layer_of(&self, city: CityID) -> Option<usize> {
+        self.layers.iter().position(|cities| cities.contains(&city))
+    }
+
+    pub fn start_city(&self) -> Option<CityID> {
+        self.layers
+            .first()
+            .and_then(|cities| cities.first())
+            .copied()
+    }
+
+    pub fn roads(&self) -> &[Road] {
+        &self.roads
+    }
+
+    pub fn roads_mut(&mut self) -> &mut [Road] {
+        &mut self.roads
+    }
+
+    // every road that touches a city, in either direction
+    pub fn roads_at(&self, city: CityID) -> impl Iterator<Item = &Road> {
+        self.roads
+            .iter()
+            .filter(move |road| road.from == city || road.to == city)
+    }
+
+    // the road that can be taken from one city to another, if there is one
+    pub fn road_between(&self, from: CityID, to: CityID) -> Option<&Road> {
+        self.roads
+            .iter()
+            .find(|road| road.leads_from(from) == Some(to))
+    }
+
+    // cities that can be travelled to from this city, in the order their roads were built
+    pub fn successors(&self, city: CityID) -> Vec<CityID> {
+        self.roads
+            .iter()
+            .filter_map(|road| road.leads_from(city))
+            .collect()
+    }
+
+    // cities that this city can be travelled to from
+    pub fn predecessors(&self, city: CityID) -> Vec<CityID> {
+        self.roads
+            .iter()
+            .filter_map(|road| road.leads_to(city))
+            .collect()
+    }
+
+    // successors in a later layer than this city, i.e. further away from the calamity
+    pub fn onward(&self, city: CityID) -> Vec<CityID> {
+        let layer = self.layer_of(city);
+        self.successors(city)
+            .into_iter()
+            .filter(|&next| self.layer_of(next) > layer)
+            .collect()
+    }
+
+    // every city that can be reached from this city, including itself
+    pub fn reachable_from(&self, city: CityID) -> BTreeSet<CityID> {
+        let mut reached = BTreeSet::from([city]);
+        let mut to_visit = VecDeque::from([city]);
+        while let Some(current) = to_visit.pop_front() {
+            for next in self.successors(current) {
+                if reached.insert(next) {
+                    to_visit.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+
+    // every city within a number of roads of this city, travelling either way down them, including itself
+    pub fn nearby(&self, city: CityID, hops: usize) -> BTreeSet<CityID> {
+        let mut reached = BTreeSet::from([city]);
+        let mut to_visit = VecDeque::from([(city, 0)]);
+        while let Some((current, distance)) = to_visit.pop_front() {
+            if distance == hops {
+                continue;
+            }
+            for road in self.roads_at(current) {
+                let next = if road.from == current {
+                    road.to
+                } else {
+                    road.from
+                };
+                if reached.insert(next) {
+                    to_visit.push_back((next, distance + 1));
+                }
+            }
+        }
+        reached
+    }
+
+    // the shortest route between two cities by road length, including both ends
+    pub fn shortest_path(&self, from: CityID, to: CityID) -> Option<Vec<CityID>> {
+        self.cheapest_path(from, |city| city == to, |road, _| road.length)
+    }
+
+    // the cheapest route from a city to the nearest city that's a goal, including both ends.
+    // each road costs whatever `cost` says it does to travel down into the given city
+    pub fn cheapest_path(
+        &self,
+        from: CityID,
+        is_goal: impl Fn(CityID) -> bool,
+        cost: impl Fn(&Road, CityID) -> usize,
+    ) -> Option<Vec<CityID>> {
+        let mut distances: BTreeMap<CityID, usize> = BTreeMap::from([(from, 0)]);
+        let mut came_from: BTreeMap<CityID, CityID> = BTreeMap::new();
+        let mut queue = BinaryHeap::from([Reverse((0, from))]);
+
+        while let Some(Reverse((distance, current))) = queue.pop() {
+            if is_goal(current) {
+                // walk back along the route
+                let mut path = vec![current];
+                let mut city = current;
+                while let Some(&previous) = came_from.get(&city) {
+                    path.push(previous);
+                    city = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if distances.get(&current).is_some_and(|&best| distance > best) {
+                continue; // already found a shorter way here
+            }
+            for road in self.roads.iter() {
+                if let Some(next) = road.leads_from(current) {
+                    let next_distance = distance + cost(road, next);
+                    if next_distance < *distances.get(&next).unwrap_or(&usize::MAX) {
+                        distances.insert(next, next_distance);
+                        came_from.insert(next, current);
+                        queue.push(Reverse((next_distance, next)));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -1,20 +1,46 @@
+pub mod calamity;
+pub mod config;
+pub mod error;
+pub mod export;
+#[cfg(feature = "godot")]
 pub mod godot;
+pub mod graph;
+pub mod map;
+pub mod narration;
+pub mod relationship;
+pub mod route;
+pub mod save;
 pub mod world;
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // the seed most tests generate their world with, so any failure can be reproduced
+    const SEED: u64 = 2024;
+
+    // a world generated with the test seed, before anything has happened in it
+    fn test_world(config: config::WorldConfig) -> world::World {
+        world::World::generate_world_with_seed(config, SEED).unwrap()
+    }
+
+    // a world generated with the test seed, with its whole history
+    fn test_history(config: config::WorldConfig) -> world::World {
+        let mut world = test_world(config);
+        world.generate_events().unwrap();
+        world
+    }
+
     #[test]
     fn world_generator() {
         use crate::world::*;
 
-        let world = world::World::generate_world();
-        println!("{:?}", world.layers[0]);
-        println!("{:?}", world.layers[1]);
-        println!("{:?}", world.layers[2]);
-        println!("{:?}", world.layers[3]);
-        println!("{:?}", world.layers[4]);
+        let world = world::World::generate_world(config::WorldConfig::default()).unwrap();
+        println!("{:?}", world.roads.layer(0));
+        println!("{:?}", world.roads.layer(1));
+        println!("{:?}", world.roads.layer(2));
+        println!("{:?}", world.roads.layer(3));
+        println!("{:?}", world.roads.layer(4));
         println!("{:?}", world.characters[&CharacterID(0)]);
         println!("{:?}", world.characters[&CharacterID(1)]);
         println!("{:?}", world.characters[&CharacterID(2)]);
@@ -31,7 +57,7 @@ mod tests {
 
             println!(
                 "{:?}",
-                world.cities.get(&CityID(city_id)).unwrap().neighbours
+                world.roads.successors(CityID(city_id))
             );
             println!("{:?}", world.cities.get(&CityID(city_id)).unwrap().name);
         }
@@ -40,8 +66,8 @@ mod tests {
 
     #[test]
     fn event_generator() {
-        let mut world = world::World::generate_world();
-        world.generate_events();
+        let mut world = world::World::generate_world(config::WorldConfig::default()).unwrap();
+        world.generate_events().unwrap();
         println!("\nEvent Display:");
         for event_id in 0..world.event_id_counter {
             let &ref event = world.events.get(&world::EventID(event_id)).unwrap();
@@ -74,6 +100,1148 @@ mod tests {
         }
     }
 
+    #[test]
+    fn seeded_generation_is_reproducible() {
+        fn history_summary(seed: u64) -> Vec<String> {
+            let mut world =
+                world::World::generate_world_with_seed(config::WorldConfig::default(), seed)
+                    .unwrap();
+            world.generate_events().unwrap();
+            let mut summary: Vec<String> = world
+                .characters
+                .values()
+                .map(|character| character.name.clone())
+                .collect();
+            summary.extend(world.events.values().map(|event| event.summary.clone()));
+            summary
+        }
+
+        assert_eq!(history_summary(2024), history_summary(2024));
+    }
+
+    #[test]
+    fn road_graph_queries() {
+        use crate::graph::*;
+        use crate::world::CityID;
+
+        // 0 <-> 1 -> 2, 0 <-> 3 <-> 2, with the road through 3 being longer
+        let mut roads = RoadGraph::new(3);
+        roads.add_city(CityID(0), 0);
+        roads.add_city(CityID(1), 1);
+        roads.add_city(CityID(3), 1);
+        roads.add_city(CityID(2), 2);
+        roads.add_road(CityID(0), CityID(1), RoadDirection::TwoWay, 1);
+        roads.add_road(CityID(1), CityID(2), RoadDirection::OneWay, 1);
+        roads.add_road(CityID(0), CityID(3), RoadDirection::TwoWay, 2);
+        roads.add_road(CityID(3), CityID(2), RoadDirection::TwoWay, 2);
+
+        assert_eq!(roads.successors(CityID(1)), vec![CityID(0), CityID(2)]);
+        assert_eq!(roads.predecessors(CityID(1)), vec![CityID(0)]);
+        assert_eq!(roads.onward(CityID(1)), vec![CityID(2)]);
+        assert_eq!(roads.layer_of(CityID(3)), Some(1));
+        assert_eq!(roads.start_city(), Some(CityID(0)));
+        assert_eq!(
+            roads.shortest_path(CityID(0), CityID(2)),
+            Some(vec![CityID(0), CityID(1), CityID(2)])
+        );
+        // the one way road can't be taken back
+        assert_eq!(
+            roads.shortest_path(CityID(2), CityID(0)),
+            Some(vec![CityID(2), CityID(3), CityID(0)])
+        );
+        assert_eq!(roads.reachable_from(CityID(2)).len(), 4);
+        assert_eq!(roads.nearby(CityID(1), 1).len(), 3);
+        // roads can be made to cost more than their length
+        assert_eq!(
+            roads.cheapest_path(
+                CityID(0),
+                |city| city == CityID(2),
+                |road, next| road.length + if next == CityID(1) { 10 } else { 0 }
+            ),
+            Some(vec![CityID(0), CityID(3), CityID(2)])
+        );
+
+        // every city in a generated world can be reached from the start city
+        let world = test_world(config::WorldConfig::default());
+        let start_city = world.roads.start_city().unwrap();
+        assert_eq!(
+            world.roads.reachable_from(start_city).len(),
+            world.cities.len()
+        );
+    }
+
+    #[test]
+    fn map_generators() {
+        use crate::map::MapKind;
+
+        for map in [
+            MapKind::LayeredFunnel,
+            MapKind::RiverDelta,
+            MapKind::Ring,
+            MapKind::Clustered,
+        ] {
+            let config = config::WorldConfig {
+                map,
+                ..Default::default()
+            };
+            let world = test_history(config);
+
+            // everyone starts in the one start city, and can get anywhere from there
+            let start_city = world.roads.start_city().unwrap();
+            assert_eq!(world.roads.layer(0), &[start_city]);
+            assert_eq!(
+                world.roads.reachable_from(start_city).len(),
+                world.cities.len()
+            );
+            assert_eq!(world.roads.num_layers(), world.config.num_layers);
+
+            // no two cities are in the same place
+            for (&city_id1, city1) in &world.cities {
+                for (&city_id2, city2) in &world.cities {
+                    if city_id1 != city_id2 {
+                        assert_ne!(city1.position, city2.position);
+                    }
+                }
+            }
+        }
+
+        let config = config::WorldConfig::from_toml("map = \"river_delta\"").unwrap();
+        assert_eq!(config.map, MapKind::RiverDelta);
+    }
+
+    #[test]
+    fn city_traits_bias_items() {
+        use crate::world::*;
+
+        let mut world = test_world(config::WorldConfig::default());
+        let start_city = world.roads.start_city().unwrap();
+        world.cities.get_mut(&start_city).unwrap().traits.craft = Craft::Metalwork;
+
+        // items are made in the start city, so most of them should be metalwork
+        let mut rng = world::seeded_rng(SEED, world::EVENTGEN_STREAM);
+        let traits = world.cities[&start_city].traits;
+        let metalwork = (0..1000)
+            .filter(|_| ItemType::made_in(&traits, &mut rng).craft() == Craft::Metalwork)
+            .count();
+        assert!(metalwork > 400);
+
+        world.generate_events().unwrap();
+        assert!(world.cities[&start_city]
+            .traits
+            .describe()
+            .contains("metalwork"));
+    }
+
+    #[test]
+    fn moves_take_time() {
+        use crate::world::*;
+
+        let world = test_history(config::WorldConfig::default());
+
+        // every move lasts as long as the road it was along, and nobody arrives before they set off
+        let mut moves = 0;
+        for (event_id, event) in &world.events {
+            if event.event_type != EventType::EventMove {
+                continue;
+            }
+            moves += 1;
+            let end_time = event.end_time.unwrap();
+            assert!(end_time > event.start_time);
+
+            // items carried on the journey arrive when the character does
+            for item in world.items.values() {
+                for record in &item.owner_records {
+                    if record.event == Some(*event_id) {
+                        assert_eq!(record.time, end_time);
+                    }
+                }
+            }
+        }
+        assert!(moves > 0);
+    }
+
+    #[test]
+    fn cities_change_over_time() {
+        let config = config::WorldConfig {
+            max_time: 20,
+            found_chance: 1.0,
+            rename_chance: 0.2,
+            ..Default::default()
+        };
+        let mut world = test_world(config);
+        let num_cities = world.cities.len();
+        world.generate_events().unwrap();
+
+        // new cities are founded ahead of the calamity, and can still be fled through
+        assert!(world.cities.len() > num_cities);
+        let start_city = world.roads.start_city().unwrap();
+        assert_eq!(
+            world.roads.reachable_from(start_city).len(),
+            world.cities.len()
+        );
+
+        // the calamity sits on the start city long enough for it to be abandoned and ruined
+        let start = &world.cities[&start_city];
+        let abandoned = start.abandoned.unwrap();
+        assert!(start.ruined.unwrap() >= abandoned);
+        assert!(start.was_standing_at(0));
+        assert!(!start.was_standing_at(abandoned));
+
+        // city events are only recorded once, even when lots of characters are in them
+        for city in world.cities.values() {
+            let mut events = city.events.clone();
+            events.dedup();
+            assert_eq!(events, city.events);
+
+            // renamed cities remember what they used to be called
+            for former_name in &city.former_names {
+                if let Some(before) = former_name.until.checked_sub(1) {
+                    assert_eq!(city.name_at(before), former_name.name);
+                }
+            }
+            assert_eq!(city.name_at(world.config.max_time), city.name);
+        }
+    }
+
+    #[test]
+    fn world_exports() {
+        use crate::world::*;
+
+        let world = test_history(config::WorldConfig::default());
+
+        // every city and road is in the graph, and the start city is coloured in
+        let dot = export::world_to_dot(&world).unwrap();
+        assert!(dot.starts_with("digraph world {"));
+        for (city_id, city) in &world.cities {
+            assert!(dot.contains(&format!("city{} [label=\"{}", city_id.0, city.name)));
+        }
+        assert_eq!(dot.matches(" -> ").count(), world.roads.roads().len());
+        let start_city = world.roads.start_city().unwrap();
+        assert!(world.cities[&start_city].calamity_arrived.is_some());
+
+        let svg = export::world_to_svg(&world).unwrap();
+        assert_eq!(svg.matches("<circle").count(), world.cities.len());
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        // each item's journey is drawn over the map, one edge or line for each step from city to city
+        let mut hops = 0;
+        for (&item_id, item) in &world.items {
+            let mut stops: Vec<CityID> = Vec::new();
+            for record in &item.owner_records {
+                let city_id = record.location().unwrap();
+                if stops.last() != Some(&city_id) {
+                    stops.push(city_id);
+                }
+            }
+            let item_hops = stops.len() - 1;
+            hops += item_hops;
+
+            let dot = export::item_to_dot(&world, item_id).unwrap();
+            assert_eq!(
+                dot.matches(" -> ").count(),
+                world.roads.roads().len() + item_hops
+            );
+            let svg = export::item_to_svg(&world, item_id).unwrap();
+            assert_eq!(svg.matches("<polyline").count(), item_hops);
+            for pair in stops.windows(2) {
+                let (from, to) = (&world.cities[&pair[0]], &world.cities[&pair[1]]);
+                assert!(svg.contains(&format!(
+                    "<polyline points=\"{},{} {},{}\"",
+                    from.position.x, from.position.y, to.position.x, to.position.y
+                )));
+            }
+        }
+        assert!(hops > 0);
+        assert!(export::item_to_svg(&world, ItemID(1000)).is_err());
+    }
+
+    #[test]
+    fn calamity_kinds() {
+        use crate::calamity::CalamityKind;
+        use crate::world::*;
+
+        for calamity in [
+            CalamityKind::Fire,
+            CalamityKind::Flood,
+            CalamityKind::Plague,
+            CalamityKind::Famine,
+        ] {
+            let config = config::WorldConfig {
+                calamity,
+                max_time: 20,
+                outbreak_chance: 0.0,
+                ..Default::default()
+            };
+            let world = test_history(config);
+
+            // every calamity gets somewhere
+            assert!(world
+                .cities
+                .values()
+                .any(|city| city.calamity_arrived.is_some()));
+
+            // only the flood takes items, and lost items stay lost
+            let losses: Vec<&Event> = world
+                .events
+                .values()
+                .filter(|event| matches!(event.event_type, EventType::EventLoss(_)))
+                .collect();
+            if calamity != CalamityKind::Flood {
+                assert!(losses.is_empty());
+            }
+            // and never from the dead
+            for loss in &losses {
+                let death = world.characters[&loss.characters[0]]
+                    .events
+                    .iter()
+                    .map(|event_id| &world.events[event_id])
+                    .find(|event| matches!(event.event_type, EventType::EventDeath(_)));
+                assert!(death.is_none_or(|death| death.start_time >= loss.start_time));
+            }
+            for item in world.items.values() {
+                let lost = item
+                    .owner_records
+                    .iter()
+                    .position(|record| record.new_owner.is_none());
+                if let Some(lost) = lost {
+                    assert_eq!(lost, item.owner_records.len() - 1);
+                }
+            }
+        }
+
+        // the fire reaches cities sooner than the flood and the famine, and kills more people than the flood.
+        // no cities are founded, so each seed has the same map whatever the calamity
+        let spread = |calamity| {
+            let (mut arrival_times, mut deaths) = (0, 0);
+            for seed in 0..5 {
+                let config = config::WorldConfig {
+                    calamity,
+                    max_time: 30,
+                    outbreak_chance: 0.0,
+                    found_chance: 0.0,
+                    ..Default::default()
+                };
+                let mut world = world::World::generate_world_with_seed(config, seed).unwrap();
+                world.generate_events().unwrap();
+                // cities it never reached count as reached just after the end
+                arrival_times += world
+                    .cities
+                    .values()
+                    .map(|city| city.calamity_arrived.unwrap_or(world.config.max_time + 1))
+                    .sum::<usize>();
+                deaths += world
+                    .events
+                    .values()
+                    .filter(|event| event.event_type == EventType::EventDeath(DeathCause::Calamity))
+                    .count();
+            }
+            (arrival_times, deaths)
+        };
+        let (fire_arrival_times, fire_deaths) = spread(CalamityKind::Fire);
+        let (flood_arrival_times, flood_deaths) = spread(CalamityKind::Flood);
+        let (famine_arrival_times, _) = spread(CalamityKind::Famine);
+        assert!(fire_arrival_times < flood_arrival_times);
+        assert!(fire_arrival_times < famine_arrival_times);
+        assert!(fire_deaths > flood_deaths);
+
+        let config = config::WorldConfig::from_toml("calamity = \"plague\"").unwrap();
+        assert_eq!(config.calamity, CalamityKind::Plague);
+    }
+
+    #[test]
+    fn calamity_spreads_along_roads() {
+        let config = config::WorldConfig {
+            max_time: 40,
+            found_chance: 0.0,
+            outbreak_chance: 0.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+
+        // the calamity starts in the start city
+        let start_city = world.roads.start_city().unwrap();
+        let freq = world.config.calamity_freq;
+        assert_eq!(world.cities[&start_city].calamity_arrived, Some(freq));
+
+        // and only gets anywhere else down a road from a city it had been in for long enough
+        let calamity = world.config.calamity.calamity();
+        for (&city_id, city) in &world.cities {
+            let arrived = match city.calamity_arrived {
+                Some(arrived) if city_id != start_city => arrived,
+                _ => continue,
+            };
+            let came_from = world
+                .roads
+                .predecessors(city_id)
+                .into_iter()
+                .any(|previous| {
+                    let road = world.roads.road_between(previous, city_id).unwrap();
+                    world.cities[&previous]
+                        .calamity_arrived
+                        .is_some_and(|previous_arrived| {
+                            previous_arrived + calamity.spread_delay(freq, road.length) <= arrived
+                        })
+                });
+            assert!(came_from);
+        }
+
+        // a calamity that never spreads stays where it started
+        let config = config::WorldConfig {
+            max_time: 40,
+            calamity_spread_chance: 0.0,
+            outbreak_chance: 0.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+        let reached = world
+            .cities
+            .values()
+            .filter(|city| city.calamity_arrived.is_some())
+            .count();
+        assert_eq!(reached, 1);
+    }
+
+    #[test]
+    fn calamity_milestones_are_events() {
+        use crate::calamity::CalamityKind;
+        use crate::world::EventType;
+
+        let config = config::WorldConfig {
+            max_time: 40,
+            calamity_duration: 5,
+            outbreak_chance: 0.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+
+        // every city the calamity reached has the events to show for it, once each
+        let mut receded = 0;
+        for (&city_id, city) in &world.cities {
+            let city_events: Vec<_> = city.events.iter().map(|id| &world.events[id]).collect();
+            let arrivals: Vec<_> = city_events
+                .iter()
+                .filter(|event| {
+                    event.event_type == EventType::EventCalamityArrival(city_id, CalamityKind::Fire)
+                })
+                .collect();
+            let recessions: Vec<_> = city_events
+                .iter()
+                .filter(|event| {
+                    event.event_type
+                        == EventType::EventCalamityRecession(city_id, CalamityKind::Fire)
+                })
+                .collect();
+            assert!(arrivals.len() <= 1 && recessions.len() <= 1);
+            assert_eq!(
+                arrivals.first().map(|event| event.start_time),
+                city.calamity_arrived
+            );
+            assert_eq!(
+                recessions.first().map(|event| event.start_time),
+                city.calamity_receded
+            );
+            if let (Some(arrived), Some(left)) = (city.calamity_arrived, city.calamity_receded) {
+                assert!(left >= arrived + world.config.calamity_duration);
+                receded += 1;
+            }
+        }
+        assert!(receded > 0);
+
+        // the start city always gets the calamity first
+        let start_city = world.roads.start_city().unwrap();
+        assert!(world.cities[&start_city].events.iter().any(|id| {
+            world.events[id].event_type
+                == EventType::EventCalamityArrival(start_city, CalamityKind::Fire)
+        }));
+    }
+
+    #[test]
+    fn plague_without_anyone_to_catch_it_starts_in_the_city() {
+        use crate::calamity::{Calamities, CalamityKind};
+
+        let config = config::WorldConfig {
+            calamity: CalamityKind::Plague,
+            ..Default::default()
+        };
+        let world = world::World::generate_world_with_seed(config, 5).unwrap();
+        let start_city = world.roads.start_city().unwrap();
+        let cities = || world.cities.keys().collect();
+        let mut rng = <world::WorldRng as rand::SeedableRng>::seed_from_u64(0);
+
+        // with nobody there to fall ill, the plague is in the city itself
+        let mut calamities =
+            Calamities::new(cities(), &world.roads, &world.config, &[], &mut rng).unwrap();
+        let state = &mut calamities.states[0];
+        assert_eq!(state.patient_zero, None);
+        let start_time = state.start_time;
+        CalamityKind::Plague
+            .calamity()
+            .spread(start_time, state, &world.roads, &mut rng);
+        assert!(state.reached.contains_key(&start_city));
+        assert!(state.infected.is_empty());
+    }
+
+    #[test]
+    fn fleeing_avoids_the_calamity() {
+        use crate::calamity::Calamities;
+        use crate::graph::*;
+        use crate::route::*;
+        use crate::world::CityID;
+
+        // 0 <-> 1 <-> 2, 0 <-> 3 <-> 2, with the road through 3 being longer
+        let mut world = test_world(config::WorldConfig::default());
+        let mut roads = RoadGraph::new(3);
+        roads.add_city(CityID(0), 0);
+        roads.add_city(CityID(1), 1);
+        roads.add_city(CityID(3), 1);
+        roads.add_city(CityID(2), 2);
+        roads.add_road(CityID(0), CityID(1), RoadDirection::TwoWay, 1);
+        roads.add_road(CityID(1), CityID(2), RoadDirection::TwoWay, 1);
+        roads.add_road(CityID(0), CityID(3), RoadDirection::TwoWay, 2);
+        roads.add_road(CityID(3), CityID(2), RoadDirection::TwoWay, 2);
+        world.roads = roads;
+
+        let mut rng = <world::WorldRng as rand::SeedableRng>::seed_from_u64(0);
+        let mut calamities = Calamities::new(
+            world.cities.keys().collect(),
+            &world.roads,
+            &world.config,
+            &[],
+            &mut rng,
+        )
+        .unwrap();
+        calamities.states[0].reach(CityID(1), 0);
+
+        // knowing the calamity is in 1, a character in 0 goes the long way round to get ahead of it
+        let known = known_calamity(&world, &calamities, CityID(0));
+        assert!(known.contains(&CityID(1)));
+        assert_eq!(
+            choose_haven(&world, &known, CityID(0), &mut rng),
+            Some(CityID(2))
+        );
+        assert_eq!(
+            plan_route(&world, &known, CityID(0), CityID(2)),
+            Some(vec![CityID(0), CityID(3), CityID(2)])
+        );
+
+        // someone who can't see that far takes the short way
+        world.config.perception_range = 0;
+        let known = known_calamity(&world, &calamities, CityID(0));
+        assert!(known.is_empty());
+        assert_eq!(
+            plan_route(&world, &known, CityID(0), CityID(2)),
+            Some(vec![CityID(0), CityID(1), CityID(2)])
+        );
+    }
+
+    #[test]
+    fn calamities_break_out_over_time() {
+        use crate::world::EventType;
+
+        let config = config::WorldConfig {
+            max_time: 60,
+            outbreak_chance: 0.5,
+            max_calamities: 10,
+            calamity_duration: 5,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+
+        // every calamity starts somewhere, so count the cities they each started in
+        let arrivals: Vec<_> = world
+            .events
+            .values()
+            .filter_map(|event| match event.event_type {
+                EventType::EventCalamityArrival(city_id, kind) => {
+                    Some((event.start_time, city_id, kind))
+                }
+                _ => None,
+            })
+            .collect();
+        let first_arrival = arrivals.first().unwrap();
+        assert_eq!(first_arrival.0, world.config.calamity_freq);
+        assert_eq!(first_arrival.2, world.config.calamity);
+
+        // later calamities break out somewhere else, and some of them after others have already left
+        let mut kinds = Vec::new();
+        for &(_, _, kind) in &arrivals {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        assert!(kinds.len() > 1);
+        let first_recession = world
+            .events
+            .values()
+            .find(|event| matches!(event.event_type, EventType::EventCalamityRecession(..)))
+            .unwrap();
+        assert!(arrivals
+            .iter()
+            .any(|&(arrived, _, _)| arrived > first_recession.start_time));
+
+        // with no outbreaks, there's only ever the one calamity
+        let config = config::WorldConfig {
+            max_time: 60,
+            outbreak_chance: 0.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+        assert!(world.events.values().all(|event| match event.event_type {
+            EventType::EventCalamityArrival(_, kind) => kind == world.config.calamity,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn personalities_shape_behaviour() {
+        use crate::world::*;
+
+        let greedy = Personality {
+            greed: TraitLevel::High,
+            sentimentality: TraitLevel::High,
+            ..Default::default()
+        };
+        let generous = Personality {
+            greed: TraitLevel::Low,
+            ..Default::default()
+        };
+        assert_eq!(greedy.giving_chance(1.0, false), 0.5);
+        assert_eq!(greedy.giving_chance(1.0, true), 0.25);
+        assert_eq!(generous.giving_chance(1.0, true), 1.0);
+        assert_eq!(Personality::default().move_weight(10), 10);
+
+        // a low trait makes something less likely, but never rules it out
+        let timid = Personality {
+            wanderlust: TraitLevel::Low,
+            sociability: TraitLevel::Low,
+            caution: TraitLevel::Low,
+            ..Default::default()
+        };
+        for weight in 1..10 {
+            assert!(timid.move_weight(weight) > 0);
+            assert!(timid.flee_weight(weight) > 0);
+            assert!(timid.encounter_weight(weight) > 0);
+            assert!(timid.idle_weight(weight) > 0);
+        }
+        assert_eq!(
+            greedy.strong_traits(),
+            vec![PersonalityTrait::Greed, PersonalityTrait::Sentimentality]
+        );
+
+        // restless people are readier to move on than homebodies
+        let with_wanderlust = |wanderlust: TraitLevel| Personality {
+            wanderlust,
+            ..Default::default()
+        };
+        assert!(
+            with_wanderlust(TraitLevel::High).move_weight(10)
+                > with_wanderlust(TraitLevel::Average).move_weight(10)
+        );
+        assert!(
+            with_wanderlust(TraitLevel::Average).move_weight(10)
+                > with_wanderlust(TraitLevel::Low).move_weight(10)
+        );
+    }
+
+    #[test]
+    fn relationships_grow_from_encounters() {
+        use crate::relationship::*;
+        use crate::world::*;
+
+        let config = config::WorldConfig {
+            num_characters: 20,
+            max_time: 30,
+            family_chance: 1.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 7).unwrap();
+        world.generate_events().unwrap();
+
+        // everyone but the first character has family, from the start or from when they were born
+        for (&character_id, character) in world.characters.iter().skip(1) {
+            assert!(character
+                .relationships
+                .values()
+                .any(|relationship| relationship.kinds[0]
+                    == (character.born.max(0) as usize, RelationshipKind::Family)));
+            for (&other_id, known) in &character.relationships {
+                assert_eq!(relationship(&world, other_id, character_id), Some(known));
+            }
+        }
+
+        // everyone who met knows each other, as many times as they met
+        let mut meetings: std::collections::BTreeMap<(CharacterID, CharacterID), usize> =
+            std::collections::BTreeMap::new();
+        for event in world.events.values() {
+            if event.event_type == EventType::EventEncounter {
+                let (first, second) = (event.characters[0], event.characters[1]);
+                *meetings
+                    .entry((first.min(second), first.max(second)))
+                    .or_default() += 1;
+            }
+        }
+        assert!(!meetings.is_empty());
+        for (&(first, second), &times) in &meetings {
+            assert_eq!(
+                relationship(&world, first, second).unwrap().encounters,
+                times
+            );
+        }
+
+        // once someone has met someone in a city, whoever started it, they don't go on to meet anyone
+        // else there until they've moved on
+        for (&character_id, character) in &world.characters {
+            let mut met = false;
+            for event in character
+                .events
+                .iter()
+                .map(|event_id| &world.events[event_id])
+            {
+                match event.event_type {
+                    EventType::EventMove if event.characters[0] == character_id => met = false,
+                    EventType::EventEncounter if event.summary.contains(" in City ") => {
+                        if event.characters[0] == character_id {
+                            assert!(!met, "{}", event.summary);
+                        }
+                        met = true;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        // people who kept meeting got closer, or fell out
+        assert!(world.characters.values().any(|character| {
+            character.relationships.values().any(|relationship| {
+                matches!(
+                    relationship.kind(),
+                    RelationshipKind::Friend | RelationshipKind::Rival | RelationshipKind::Lover
+                )
+            })
+        }));
+
+        let friends = Relationship {
+            encounters: 3,
+            kinds: vec![
+                (2, RelationshipKind::Acquaintance),
+                (5, RelationshipKind::Friend),
+            ],
+        };
+        assert_eq!(
+            describe(None, 0, "her", "Tennia"),
+            "a stranger named Tennia"
+        );
+        assert_eq!(
+            describe(Some(&friends), 1, "her", "Tennia"),
+            "a stranger named Tennia"
+        );
+        assert_eq!(
+            describe(Some(&friends), 5, "her", "Tennia"),
+            "her friend Tennia"
+        );
+        assert_eq!(
+            describe(Some(&friends), 20, "her", "Tennia"),
+            "her old friend Tennia"
+        );
+    }
+
+    #[test]
+    fn births_span_generations() {
+        use crate::relationship::*;
+        use crate::world::*;
+
+        let config = config::WorldConfig {
+            max_time: 40,
+            birth_chance: 0.2,
+            come_of_age: 5,
+            outbreak_chance: 0.0,
+            ..Default::default()
+        };
+        let mut world = world::World::generate_world_with_seed(config, 3).unwrap();
+        world.generate_events().unwrap();
+
+        let births: Vec<&Event> = world
+            .events
+            .values()
+            .filter(|event| event.event_type == EventType::EventBirth)
+            .collect();
+        assert!(!births.is_empty());
+        for birth in &births {
+            let child_id = birth.characters[0];
+            let child = &world.characters[&child_id];
+            assert_eq!(child.born, birth.start_time as isize);
+            assert_eq!(
+                world.events[&child.events[0]].event_type,
+                EventType::EventBirth
+            );
+
+            // children are their parents' kin, old enough to have them, and named after them
+            let parents = &birth.characters[1..];
+            assert!(!parents.is_empty());
+            for &parent_id in parents {
+                let parent = &world.characters[&parent_id];
+                assert!(parent.age_at(birth.start_time) >= world.config.come_of_age);
+                assert_eq!(
+                    relationship(&world, child_id, parent_id).unwrap().kind(),
+                    RelationshipKind::Family
+                );
+            }
+            let first_parent = &world.characters[&parents[0]].name;
+            assert_eq!(child.name[..2], first_parent[..2]);
+        }
+
+        // some children grew up to have children of their own
+        assert!(births
+            .iter()
+            .any(|birth| world.characters[&birth.characters[1]].born > 0));
+    }
+
+    #[test]
+    fn shipped_config_has_births() {
+        use crate::world::*;
+
+        let config = config::WorldConfig::load(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../godot/writing/world_config.toml"
+        ))
+        .unwrap();
+        for seed in 0..5 {
+            let mut world = world::World::generate_world_with_seed(config.clone(), seed).unwrap();
+            world.generate_events().unwrap();
+            assert!(
+                world
+                    .events
+                    .values()
+                    .any(|event| event.event_type == EventType::EventBirth),
+                "nobody was born with seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn characters_die_of_many_causes() {
+        use crate::world::*;
+
+        let mut causes = Vec::new();
+        for seed in 0..3 {
+            let config = config::WorldConfig {
+                num_characters: 12,
+                max_time: 60,
+                lifespan: 20,
+                come_of_age: 5,
+                illness_chance: 0.05,
+                accident_chance: 0.05,
+                violence_chance: 0.5,
+                ..Default::default()
+            };
+            let mut world = world::World::generate_world_with_seed(config, seed).unwrap();
+            world.generate_events().unwrap();
+
+            for (&character_id, character) in &world.characters {
+                assert!(character.lifespan > 0);
+                let death = character
+                    .events
+                    .iter()
+                    .map(|event_id| &world.events[event_id])
+                    .find(|event| matches!(event.event_type, EventType::EventDeath(_)));
+                let death = match death {
+                    Some(death) => death,
+                    None => {
+                        // nobody outlives their lifespan
+                        assert!(character.age_at(world.config.max_time) < character.lifespan);
+                        continue;
+                    }
+                };
+                assert_eq!(death.characters, vec![character_id]);
+                let cause = character.death_cause(&world).unwrap();
+                assert_eq!(death.event_type, EventType::EventDeath(cause));
+                let age = character.age_at(death.start_time);
+                if cause == DeathCause::OldAge {
+                    assert_eq!(age, character.lifespan);
+                } else {
+                    assert!(age < character.lifespan);
+                }
+                // accidents happen on the road, which is as far as the traveller's journey got
+                if cause == DeathCause::Accident {
+                    assert!(death.summary.contains("on the road from"));
+                    let journey = character
+                        .events
+                        .iter()
+                        .map(|event_id| &world.events[event_id])
+                        .rfind(|event| event.event_type == EventType::EventMove)
+                        .unwrap();
+                    assert_eq!(journey.end_time, Some(death.start_time));
+                }
+                causes.push(cause);
+            }
+        }
+
+        for cause in [
+            DeathCause::OldAge,
+            DeathCause::Illness,
+            DeathCause::Accident,
+            DeathCause::Calamity,
+        ] {
+            assert!(causes.contains(&cause), "nobody died {}", cause);
+        }
+    }
+
+    #[test]
+    fn accidents_leave_items_short_of_the_destination() {
+        use crate::world::*;
+
+        let mut victims_items = 0;
+        for seed in 0..3 {
+            let config = config::WorldConfig {
+                accident_chance: 0.2,
+                ..Default::default()
+            };
+            let mut world = world::World::generate_world_with_seed(config, seed).unwrap();
+            world.generate_events().unwrap();
+
+            for (death_id, death) in &world.events {
+                if death.event_type != EventType::EventDeath(DeathCause::Accident) {
+                    continue;
+                }
+                let character = &world.characters[&death.characters[0]];
+                let journey_id = character
+                    .events
+                    .iter()
+                    .rfind(|event_id| world.events[event_id].event_type == EventType::EventMove)
+                    .unwrap();
+                let (&destination, _) = world
+                    .cities
+                    .iter()
+                    .find(|(_, city)| city.events.contains(journey_id))
+                    .unwrap();
+                assert!(!world.cities[&destination].events.contains(death_id));
+
+                // the victim's items stay wherever the road began, not where it was going
+                for item in world.items.values() {
+                    for record in &item.owner_records {
+                        if record.event == Some(*death_id) {
+                            assert_ne!(record.new_location, Some(destination));
+                            victims_items += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert!(victims_items > 0);
+    }
+
+    #[test]
+    fn world_config_loading() {
+        use crate::config::*;
+
+        let config = WorldConfig::from_toml("num_characters = 10\nnum_layers = 3").unwrap();
+        assert_eq!(config.num_characters, 10);
+        assert_eq!(config.num_layers, 3);
+        assert_eq!(config.max_time, WorldConfig::default().max_time);
+
+        let world = world::World::generate_world(config).unwrap();
+        assert_eq!(world.roads.num_layers(), 3);
+        assert_eq!(world.characters.len(), 10);
+
+        assert!(WorldConfig::from_json(r#"{"num_layers": 1}"#).is_err());
+        assert!(
+            WorldConfig::from_json(r#"{"min_cities_in_layer": 3, "max_cities_in_layer": 2}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn narration_without_godot() {
+        use crate::narration::*;
+
+        let descs = load_descriptions(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../godot/writing/descriptions.json"
+        ))
+        .unwrap();
+        let world = test_history(config::WorldConfig::default());
+
+        let narrations = narrate_world(&world, &descs).unwrap();
+        assert_eq!(narrations.len(), world.items.len());
+        for narration in &narrations {
+            assert!(!narration.description.is_empty());
+            assert!(!narration.stories.is_empty());
+        }
+    }
+
+    #[test]
+    fn world_save_and_load() {
+        let world = test_history(config::WorldConfig::default());
+
+        let path = std::env::temp_dir().join("procjam_history_save_test.json");
+        save::save_world(&world, &path).unwrap();
+        let loaded = save::load_world(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.seed, world.seed);
+        assert_eq!(loaded.config, world.config);
+        assert_eq!(loaded.roads.layers(), world.roads.layers());
+        assert_eq!(loaded.roads.roads(), world.roads.roads());
+        assert_eq!(loaded.cities.len(), world.cities.len());
+        assert_eq!(loaded.characters.len(), world.characters.len());
+        for (event_id, event) in &world.events {
+            assert_eq!(loaded.events[event_id].summary, event.summary);
+            assert_eq!(loaded.events[event_id].event_type, event.event_type);
+        }
+        for (item_id, item) in &world.items {
+            assert_eq!(loaded.items[item_id].item_type, item.item_type);
+            assert_eq!(
+                loaded.items[item_id].owner_records.len(),
+                item.owner_records.len()
+            );
+        }
+    }
+
+    #[test]
+    fn old_saves_are_migrated() {
+        use crate::world::*;
+
+        // version 0 saves were a bare world, with layers and one way neighbour lists
+        let old_save = r#"{
+            "seed": 5,
+            "config": {"num_layers": 2},
+            "cities": {
+                "0": {"name": "booshford", "neighbours": [], "events": [0]},
+                "1": {"name": "toolby", "neighbours": [0], "events": []}
+            },
+            "characters": {
+                "0": {
+                    "name": "punnu",
+                    "pronouns": {"nominative": "they", "accusative": "them", "dep_genitive": "their"},
+                    "events": [0, 1]
+                }
+            },
+            "events": {
+                "0": {
+                    "characters": [0],
+                    "start_time": 0,
+                    "end_time": null,
+                    "event_type": "EventMove",
+                    "events_happening_during": [],
+                    "summary": "Character #CharacterID(0) moved to City #CityID(0)"
+                },
+                "1": {
+                    "characters": [0],
+                    "start_time": 1,
+                    "end_time": null,
+                    "event_type": "EventMove",
+                    "events_happening_during": [],
+                    "summary": "Character #CharacterID(0) died in City #CityID(0)"
+                }
+            },
+            "items": {},
+            "city_id_counter": 2,
+            "event_id_counter": 2,
+            "character_id_counter": 1,
+            "item_id_counter": 0,
+            "layers": [[1], [0]]
+        }"#;
+        let save = save::parse_save(old_save).unwrap();
+        assert_eq!(save.version, save::SAVE_VERSION);
+        assert_eq!(save.seed, 5);
+        assert_eq!(save.config.num_layers, 2);
+        assert_eq!(save.world.events.len(), 2);
+        // deaths used to be recorded as moves
+        assert_eq!(
+            save.world.events[&EventID(0)].event_type,
+            EventType::EventMove
+        );
+        assert_eq!(
+            save.world.events[&EventID(1)].event_type,
+            EventType::EventDeath(DeathCause::Calamity)
+        );
+        assert!(save.world.characters[&CharacterID(0)].has_died(&save.world));
+        assert_eq!(save.world.roads.start_city(), Some(CityID(1)));
+        assert_eq!(save.world.roads.successors(CityID(1)), vec![CityID(0)]);
+        assert!(save.world.roads.successors(CityID(0)).is_empty());
+        assert_eq!(
+            save.world.distance_between(CityID(0), CityID(1)).unwrap(),
+            100.0
+        );
+
+        // saves from the future and files that aren't saves are refused
+        let future_save = format!(r#"{{"version": {}}}"#, save::SAVE_VERSION + 1);
+        assert!(matches!(
+            save::parse_save(&future_save),
+            Err(error::SaveError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            save::parse_save(r#"{"shop": "closed"}"#),
+            Err(error::SaveError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn calamity_milestone_saves_are_migrated() {
+        use crate::calamity::CalamityKind;
+
+        // a flooded world with a city the flood reached, emptied and left
+        let world = (0..20)
+            .map(|seed| {
+                let config = config::WorldConfig {
+                    calamity: CalamityKind::Flood,
+                    max_time: 40,
+                    calamity_duration: 5,
+                    outbreak_chance: 0.0,
+                    ..Default::default()
+                };
+                let mut world = world::World::generate_world_with_seed(config, seed).unwrap();
+                world.generate_events().unwrap();
+                world
+            })
+            .find(|world| {
+                let has = |is: fn(&world::EventType) -> bool| {
+                    world.events.values().any(|event| is(&event.event_type))
+                };
+                has(|event_type| matches!(event_type, world::EventType::EventCityFall(..)))
+                    && has(|event_type| {
+                        matches!(event_type, world::EventType::EventCalamityRecession(..))
+                    })
+            })
+            .unwrap();
+
+        // version 3 saves recorded calamity milestones with just the city
+        let mut world_json = serde_json::to_value(&world).unwrap();
+        let mut variants = std::collections::BTreeSet::new();
+        for event in world_json["events"].as_object_mut().unwrap().values_mut() {
+            let event_type = match event["event_type"].as_object_mut() {
+                Some(event_type) => event_type,
+                None => continue,
+            };
+            for (variant, fields) in event_type.iter_mut() {
+                if variant.starts_with("EventCalamity") || variant == "EventCityFall" {
+                    *fields = fields[0].clone();
+                    variants.insert(variant.clone());
+                }
+            }
+        }
+        assert_eq!(variants.len(), 3);
+        let old_save = serde_json::json!({
+            "version": 3,
+            "config": world.config,
+            "seed": world.seed,
+            "world": world_json,
+        });
+
+        let save = save::parse_save(&old_save.to_string()).unwrap();
+        assert_eq!(save.version, save::SAVE_VERSION);
+        assert_eq!(save.world.events.len(), world.events.len());
+        for (event_id, event) in &world.events {
+            assert_eq!(save.world.events[event_id].event_type, event.event_type);
+        }
+    }
+
     #[test]
     fn run_eventgen_alot() {
         for _ in 0..100 {
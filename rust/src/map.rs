@@ -0,0 +1,296 @@
+use crate::error::WorldError;
+use crate::graph::{RoadDirection, RoadGraph};
+use crate::world::{CityID, Position, World};
+use log::info;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// -- Map generators --
+
+// lays out the cities of a world and the roads between them.
+// every generator puts the start city (where the calamity begins) alone in layer 0, and every city must
+// have a road onwards to a later layer unless it's at the end of the road.
+pub trait MapGenerator {
+    fn generate<R: Rng + ?Sized>(&self, world: &mut World, rng: &mut R) -> Result<(), WorldError>;
+}
+
+// which map generator a world is generated with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MapKind {
+    #[default]
+    LayeredFunnel,
+    RiverDelta,
+    Ring,
+    Clustered,
+}
+
+impl MapKind {
+    pub fn generate<R: Rng + ?Sized>(
+        &self,
+        world: &mut World,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        info!(target: crate::world::LOG_WORLDGEN, "generating {:?} map...", self);
+        match self {
+            MapKind::LayeredFunnel => LayeredFunnel.generate(world, rng),
+            MapKind::RiverDelta => RiverDelta.generate(world, rng),
+            MapKind::Ring => Ring.generate(world, rng),
+            MapKind::Clustered => Clustered.generate(world, rng),
+        }
+    }
+}
+
+// a single start city, a few cities in each layer in between, and a single end city.
+// each city has roads to one or more random cities in the next layer.
+pub struct LayeredFunnel;
+
+impl MapGenerator for LayeredFunnel {
+    fn generate<R: Rng + ?Sized>(&self, world: &mut World, rng: &mut R) -> Result<(), WorldError> {
+        let num_layers = world.config.num_layers;
+
+        // add end city
+        world.add_city(num_layers - 1, rng);
+
+        // add in between cities - we will work from end to beginning so that each city is guaranteed to be connected to at least one city from the next layer
+        for layer in (1..num_layers - 1).rev() {
+            let num_cities =
+                rng.gen_range(world.config.min_cities_in_layer..=world.config.max_cities_in_layer);
+
+            for _ in 1..=num_cities {
+                // initialise new city
+                let new_city = world.add_city(layer, rng);
+
+                // randomly connect to other cities in next layer
+                let cities_in_next_layer = world.roads.layer(layer + 1).to_vec();
+                let num_connections = rng.gen_range(1..=cities_in_next_layer.len());
+                let cities_to_connect = cities_in_next_layer.choose_multiple(rng, num_connections);
+
+                for city_id in cities_to_connect {
+                    world.connect_cities(&new_city, city_id)?;
+                }
+            }
+        }
+
+        // add start city
+        let start_city = world.add_city(0, rng);
+
+        for city_id in world.roads.layer(1).to_vec() {
+            world.connect_cities(&start_city, &city_id)?;
+        }
+
+        Ok(())
+    }
+}
+
+// a river that splits into more and more branches on its way to the coast.
+// the roads are boats down the river, so they only go one way.
+pub struct RiverDelta;
+
+impl MapGenerator for RiverDelta {
+    fn generate<R: Rng + ?Sized>(&self, world: &mut World, rng: &mut R) -> Result<(), WorldError> {
+        let num_layers = world.config.num_layers;
+        let max_branches = world.config.max_cities_in_layer;
+
+        // the source of the river
+        world.add_city(0, rng);
+
+        for layer in 1..num_layers {
+            for upstream in world.roads.layer(layer - 1).to_vec() {
+                // the river either carries on or splits in two here
+                let num_branches = rng.gen_range(1..=2);
+                for _ in 0..num_branches {
+                    // once the layer is full, the branch flows into one of the existing cities instead
+                    let branches = world.roads.layer(layer);
+                    let downstream = if branches.len() < max_branches {
+                        world.add_city(layer, rng)
+                    } else {
+                        branches[rng.gen_range(0..branches.len())]
+                    };
+                    if world.roads.road_between(upstream, downstream).is_none() {
+                        world.add_road(upstream, downstream, RoadDirection::OneWay, 1)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// cities in a ring, with the start city on one side and the end city on the other.
+// every city in between is in the same layer as the city opposite it, so there are two ways round.
+pub struct Ring;
+
+impl MapGenerator for Ring {
+    fn generate<R: Rng + ?Sized>(&self, world: &mut World, rng: &mut R) -> Result<(), WorldError> {
+        let num_layers = world.config.num_layers;
+        let ring_size = 2 * (num_layers - 1);
+
+        let mut ring: Vec<CityID> = Vec::new();
+        for position in 0..ring_size {
+            // layers count the number of steps from the start city, whichever way round is shortest
+            let layer = position.min(ring_size - position);
+            ring.push(world.add_city(layer, rng));
+        }
+
+        for position in 0..ring_size {
+            let next_position = (position + 1) % ring_size;
+            // a ring of two cities only needs one road between them
+            if next_position == 0 && ring_size == 2 {
+                break;
+            }
+            let length = rng.gen_range(1..=2);
+            world.add_road(
+                ring[position],
+                ring[next_position],
+                RoadDirection::TwoWay,
+                length,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// regions of cities close together, with long roads between neighbouring regions.
+// every city has a road to the next region, so nobody is trapped in a region.
+pub struct Clustered;
+
+const REGION_ROAD_LENGTH: usize = 1;
+const BETWEEN_REGIONS_ROAD_LENGTH: usize = 3;
+
+impl MapGenerator for Clustered {
+    fn generate<R: Rng + ?Sized>(&self, world: &mut World, rng: &mut R) -> Result<(), WorldError> {
+        let num_layers = world.config.num_layers;
+
+        // work from the last region to the first, so there's always a next region to connect to
+        for layer in (0..num_layers).rev() {
+            // the start region only has the start city, so everyone starts in the same place
+            let num_cities = if layer == 0 {
+                1
+            } else {
+                rng.gen_range(world.config.min_cities_in_layer..=world.config.max_cities_in_layer)
+            };
+
+            let mut region: Vec<CityID> = Vec::new();
+            for _ in 0..num_cities {
+                let new_city = world.add_city(layer, rng);
+
+                // every city in a region is close to every other
+                for &other_city in &region {
+                    world.add_road(
+                        new_city,
+                        other_city,
+                        RoadDirection::TwoWay,
+                        REGION_ROAD_LENGTH,
+                    )?;
+                }
+
+                // and has a long road to the next region
+                if let Some(&next_city) = world.roads.layer(layer + 1).choose(rng) {
+                    world.add_road(
+                        new_city,
+                        next_city,
+                        RoadDirection::TwoWay,
+                        BETWEEN_REGIONS_ROAD_LENGTH,
+                    )?;
+                }
+
+                region.push(new_city);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// -- Layout --
+
+// distance between layers, and between the cities in a layer
+const LAYER_SPACING: f32 = 100.0;
+const CITY_SPACING: f32 = 60.0;
+
+// places every city on the map. each layer is a column, with its cities spread out around y = 0.
+// cities are ordered within their column by where the cities they have roads to in the previous column are,
+// so that roads cross as little as possible. this doesn't use any randomness, so it doesn't change the history.
+pub fn layout_cities(roads: &RoadGraph) -> BTreeMap<CityID, Position> {
+    let mut positions: BTreeMap<CityID, Position> = BTreeMap::new();
+
+    for (layer_i, layer) in roads.layers().iter().enumerate() {
+        // average height of the connected cities already placed, or 0 if there are none
+        let mut ordered: Vec<(f32, CityID)> = layer
+            .iter()
+            .map(|&city_id| {
+                let placed: Vec<f32> = roads
+                    .roads_at(city_id)
+                    .map(|road| {
+                        if road.from == city_id {
+                            road.to
+                        } else {
+                            road.from
+                        }
+                    })
+                    .filter_map(|other_id| positions.get(&other_id))
+                    .map(|position| position.y)
+                    .collect();
+                let height = if placed.is_empty() {
+                    0.0
+                } else {
+                    placed.iter().sum::<f32>() / placed.len() as f32
+                };
+                (height, city_id)
+            })
+            .collect();
+        ordered.sort_by(|(height1, _), (height2, _)| height1.total_cmp(height2));
+
+        let middle = (ordered.len() as f32 - 1.0) / 2.0;
+        for (city_i, &(_, city_id)) in ordered.iter().enumerate() {
+            positions.insert(
+                city_id,
+                Position {
+                    x: layer_i as f32 * LAYER_SPACING,
+                    y: (city_i as f32 - middle) * CITY_SPACING,
+                },
+            );
+        }
+    }
+
+    positions
+}
+
+// how far a character travels along a road in one tick
+const DISTANCE_PER_TICK: f32 = LAYER_SPACING;
+
+// how many ticks it takes to travel between two places on the map
+pub fn travel_time(from: &Position, to: &Position) -> usize {
+    (from.distance_to(to) / DISTANCE_PER_TICK).ceil() as usize
+}
+
+// makes sure every road takes at least as long to travel as the distance between its cities on the map,
+// so cities that are drawn far apart are also far apart to walk between
+pub fn stretch_roads(roads: &mut RoadGraph, positions: &BTreeMap<CityID, Position>) {
+    for road in roads.roads_mut() {
+        if let (Some(from), Some(to)) = (positions.get(&road.from), positions.get(&road.to)) {
+            road.length = road.length.max(travel_time(from, to));
+        }
+    }
+}
+
+// where a city founded partway through the history goes: at the bottom of its layer's column,
+// so none of the cities that are already on the map have to move
+pub fn position_in_layer(world: &World, layer: usize) -> Position {
+    let lowest = world
+        .roads
+        .layer(layer)
+        .iter()
+        .filter_map(|city_id| world.cities.get(city_id))
+        .map(|city| city.position.y)
+        .max_by(|y1, y2| y1.total_cmp(y2));
+    Position {
+        x: layer as f32 * LAYER_SPACING,
+        y: lowest.map_or(0.0, |y| y + CITY_SPACING),
+    }
+}
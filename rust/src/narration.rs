@@ -0,0 +1,654 @@
+use std::collections::HashMap;
+use std::fs::File;
+
+use crate::calamity::CalamityKind;
+use crate::error::{NarrationError, WorldError};
+use crate::relationship;
+use crate::world::*;
+use log::trace;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use strfmt::strfmt;
+use stringcase::Caser;
+
+const MAX_WEAR_DESC: usize = 2;
+// chance of bringing up what's become of a city since a story happened there,
+// so the shopkeeper doesn't mention it after every single story
+const CITY_FATE_CHANCE: f64 = 0.5;
+// chance of remembering what the calamity was doing the year a story happened
+const MILESTONE_CHANCE: f64 = 0.5;
+// chance of remarking on what the owner was like, if there's anything to remark on
+const PERSONALITY_CHANCE: f64 = 0.3;
+
+// -- Writing --
+
+#[derive(Serialize, Deserialize)]
+struct WearDescs {
+    bricabrac_wear: Vec<Vec<String>>,
+    accessory_wear: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EventLines {
+    creation_lines: Vec<Vec<String>>,
+    death_lines: Vec<Vec<String>>, // used for any cause of death without lines of its own
+    #[serde(default)]
+    death_cause_lines: DeathCauseLines,
+    move_lines: Vec<Vec<String>>,
+    exchange_lines: Vec<Vec<String>>,
+    postmortem_exchange_lines: Vec<Vec<String>>,
+    #[serde(default)]
+    lost_item_lines: Vec<Vec<String>>,
+    #[serde(default)]
+    lost_city_lines: Vec<Vec<String>>, // added after a story in a city that was later abandoned
+    #[serde(default)]
+    renamed_city_lines: Vec<Vec<String>>, // added after a story in a city that was later renamed
+    #[serde(default)]
+    calamity_arrival_lines: Vec<Vec<String>>, // added after a story from the year the calamity reached a city
+    #[serde(default)]
+    city_fell_lines: Vec<Vec<String>>, // added after a story from the year a city fell to the calamity
+    #[serde(default)]
+    calamity_receded_lines: Vec<Vec<String>>, // added after a story from the year the calamity left a city
+    #[serde(default)]
+    personality_lines: PersonalityLines, // added after a story about someone with a strong personality trait
+    _format_rules: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct PersonalityLines {
+    wanderlust: Vec<Vec<String>>,
+    sociability: Vec<Vec<String>>,
+    greed: Vec<Vec<String>>,
+    caution: Vec<Vec<String>>,
+    sentimentality: Vec<Vec<String>>,
+}
+
+impl PersonalityLines {
+    fn for_trait(&self, personality_trait: PersonalityTrait) -> &Vec<Vec<String>> {
+        match personality_trait {
+            PersonalityTrait::Wanderlust => &self.wanderlust,
+            PersonalityTrait::Sociability => &self.sociability,
+            PersonalityTrait::Greed => &self.greed,
+            PersonalityTrait::Caution => &self.caution,
+            PersonalityTrait::Sentimentality => &self.sentimentality,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct DeathCauseLines {
+    calamity: Vec<Vec<String>>,
+    old_age: Vec<Vec<String>>,
+    illness: Vec<Vec<String>>,
+    accident: Vec<Vec<String>>,
+    violence: Vec<Vec<String>>,
+}
+
+impl DeathCauseLines {
+    fn for_cause(&self, cause: DeathCause) -> &Vec<Vec<String>> {
+        match cause {
+            DeathCause::Calamity => &self.calamity,
+            DeathCause::OldAge => &self.old_age,
+            DeathCause::Illness => &self.illness,
+            DeathCause::Accident => &self.accident,
+            DeathCause::Violence => &self.violence,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DescJson {
+    initial_descriptions: HashMap<String, String>,
+    wear_descriptions: WearDescs,
+    story_intros: Vec<String>,
+    story_outros: Vec<String>,
+    event_lines: EventLines,
+}
+
+// -- Item narration --
+
+pub fn get_item_types(item: &Item) -> Result<(usize, usize, usize), NarrationError> {
+    let item_type_string = item.item_type.to_string();
+    let item_supertype_i: usize = match item.item_type {
+        // bricabrac
+        ItemType::Teapot1
+        | ItemType::Teapot2
+        | ItemType::Teapot3
+        | ItemType::Vase1
+        | ItemType::Vase2
+        | ItemType::Vase3
+        | ItemType::Cup1
+        | ItemType::Statue
+        | ItemType::Orb => 0,
+        // accessories
+        ItemType::Belt
+        | ItemType::Bracelet
+        | ItemType::Hat
+        | ItemType::Shoes1
+        | ItemType::Shoes2
+        | ItemType::Shoes3
+        | ItemType::Sunglasses
+        | ItemType::Necklace => 1,
+    };
+    let item_type_i: usize = match item.item_type {
+        ItemType::Teapot1 | ItemType::Teapot2 | ItemType::Teapot3 => 0,
+        ItemType::Vase1 | ItemType::Vase2 | ItemType::Vase3 => 1,
+        ItemType::Cup1 => 2,
+        ItemType::Statue => 3,
+        ItemType::Belt => 5,
+        ItemType::Bracelet => 6,
+        ItemType::Hat => 7,
+        ItemType::Shoes1 | ItemType::Shoes2 => 8,
+        ItemType::Sunglasses => 9,
+        ItemType::Necklace => 10,
+        // these have no writing yet
+        ItemType::Orb | ItemType::Shoes3 => {
+            return Err(NarrationError::UnknownItemType(item_type_string))
+        }
+    };
+    let item_subtype_i: usize = item_type_string
+        .chars()
+        .last()
+        .and_then(|subtype| subtype.to_digit(10))
+        .and_then(|subtype| (subtype as usize).checked_sub(1))
+        .ok_or(NarrationError::UnknownItemType(item_type_string.clone()))?;
+
+    Ok((item_supertype_i, item_type_i, item_subtype_i))
+}
+
+pub fn load_descriptions(path: &str) -> Result<DescJson, NarrationError> {
+    let desc_file = File::open(path).map_err(NarrationError::Io)?;
+    serde_json::from_reader(desc_file).map_err(NarrationError::Json)
+}
+
+pub fn generate_description<R: Rng + ?Sized>(
+    item: &Item,
+    item_types: (usize, usize, usize),
+    descs: &DescJson,
+    rng: &mut R,
+) -> Result<Vec<String>, NarrationError> {
+    let wear_desc_amt = rng.gen_range(0..=MAX_WEAR_DESC);
+    let mut description: Vec<String> = Vec::new();
+
+    let item_type_string = item.item_type.to_string();
+    let first_desc = descs
+        .initial_descriptions
+        .get(&item_type_string)
+        .ok_or(NarrationError::MissingDescription(item_type_string))?;
+    description.push(first_desc.to_string());
+
+    //desc of wear
+    let wear_descs = &descs.wear_descriptions;
+    let mut wear_format_hashmap: HashMap<String, String> = HashMap::new();
+
+    let wear_list = match item_types.0 {
+        0 => &wear_descs.bricabrac_wear,
+        _ => &wear_descs.accessory_wear,
+    };
+
+    if item_types.0 == 0 {
+        let format_descs = wear_list
+            .get(item_types.1 + 1)
+            .ok_or(NarrationError::MissingLines("bricabrac wear descriptions"))?;
+        for (i, desc) in format_descs.iter().enumerate() {
+            wear_format_hashmap.insert(i.to_string(), desc.to_string());
+        }
+    }
+
+    let wear_lines = wear_list
+        .first()
+        .ok_or(NarrationError::MissingLines("wear descriptions"))?;
+    for desc in wear_lines.choose_multiple(rng, wear_desc_amt) {
+        description.push(strfmt(desc, &wear_format_hashmap)?);
+    }
+
+    Ok(description)
+}
+
+pub fn get_records_from_time(records: &[ItemMoveRecord], time: usize) -> Vec<&ItemMoveRecord> {
+    let result: Vec<&ItemMoveRecord> = records
+        .iter()
+        .filter(|&record| record.time == time)
+        .collect();
+
+    result
+}
+
+// the city a calamity milestone happened in and the calamity it was, if the event is one
+fn milestone_of(event: &Event) -> Option<(CityID, CalamityKind)> {
+    match event.event_type {
+        EventType::EventCalamityArrival(city_id, kind)
+        | EventType::EventCityFall(city_id, kind)
+        | EventType::EventCalamityRecession(city_id, kind) => Some((city_id, kind)),
+        _ => None,
+    }
+}
+
+// something a calamity did in the given year, preferring whatever happened to the given city
+fn calamity_milestone_at(
+    world: &World,
+    time: usize,
+    city_id: CityID,
+) -> Option<(CityID, CalamityKind, &Event)> {
+    let milestones: Vec<(CityID, CalamityKind, &Event)> = world
+        .events
+        .values()
+        .filter(|event| event.start_time == time)
+        .filter_map(|event| milestone_of(event).map(|(city, kind)| (city, kind, event)))
+        .collect();
+    milestones
+        .iter()
+        .find(|&&(milestone_city, _, _)| milestone_city == city_id)
+        .or(milestones.first())
+        .copied()
+}
+
+// the calamity that had most recently reached a city by the given year. if none had got there,
+// the one that had most recently reached anywhere, and failing that the first calamity
+fn calamity_at(world: &World, city_id: CityID, time: usize) -> CalamityKind {
+    let arrivals = || {
+        world
+            .events
+            .values()
+            .filter_map(|event| match event.event_type {
+                EventType::EventCalamityArrival(arrival_city, kind) if event.start_time <= time => {
+                    Some((arrival_city, kind))
+                }
+                _ => None,
+            })
+    };
+    arrivals()
+        .rfind(|&(arrival_city, _)| arrival_city == city_id)
+        .or_else(|| arrivals().next_back())
+        .map_or(world.config.calamity, |(_, kind)| kind)
+}
+
+pub fn format_event_lines(
+    lines: &Vec<String>,
+    world: &World,
+    record: &ItemMoveRecord,
+    previous_record: Option<&ItemMoveRecord>,
+) -> Result<Vec<String>, NarrationError> {
+    let event_id = record.event_id()?;
+    let event = world
+        .events
+        .get(&event_id)
+        .ok_or(WorldError::MissingEvent(event_id))?;
+    let mut lines_formatted: Vec<String> = Vec::new();
+
+    // get format parameters ready
+    let mut format_vars: HashMap<String, String> = HashMap::new();
+    // insert owner name. lost items have no owner, so use whoever lost them
+    let owner_id = match record.new_owner {
+        Some(owner_id) => owner_id,
+        None => *event
+            .characters
+            .first()
+            .ok_or(WorldError::IncompleteRecord("owner"))?,
+    };
+    let owner = world
+        .characters
+        .get(&owner_id)
+        .ok_or(WorldError::MissingCharacter(owner_id))?;
+    format_vars.insert(
+        "owner_name".to_string(),
+        owner.name.clone().to_pascal_case(),
+    );
+    // insert city name
+    let city_id = record.location()?;
+    let city = world
+        .cities
+        .get(&city_id)
+        .ok_or(WorldError::MissingCity(city_id))?;
+    // cities are called what they were called at the time
+    format_vars.insert(
+        "city_name".to_string(),
+        city.name_at(record.time).to_pascal_case(),
+    );
+    format_vars.insert(
+        "city_current_name".to_string(),
+        city.name.clone().to_pascal_case(),
+    );
+    // insert what the city is like
+    format_vars.insert("city_trait".to_string(), city.traits.describe());
+    format_vars.insert("city_size".to_string(), city.traits.size.to_string());
+    format_vars.insert("city_culture".to_string(), city.traits.culture.to_string());
+    format_vars.insert("city_craft".to_string(), city.traits.craft.to_string());
+    format_vars.insert("city_biome".to_string(), city.traits.biome.to_string());
+    // insert pronouns of owner
+    format_vars.insert(
+        "nominative_pronoun".to_string(),
+        owner.pronouns.nominative.clone(),
+    );
+    format_vars.insert(
+        "accusative_pronoun".to_string(),
+        owner.pronouns.accusative.clone(),
+    );
+    format_vars.insert(
+        "dep_genitive_pronoun".to_string(),
+        owner.pronouns.dep_genitive.clone(),
+    );
+    // how old the owner was at the time
+    format_vars.insert(
+        "owner_age".to_string(),
+        owner.age_at(record.time).to_string(),
+    );
+    // add old owner info if applicable.
+    if event.event_type == EventType::EventEncounter {
+        let old_owner_id = *event
+            .characters
+            .get(1)
+            .ok_or(WorldError::IncompleteRecord("old owner"))?;
+        let old_owner = world
+            .characters
+            .get(&old_owner_id)
+            .ok_or(WorldError::MissingCharacter(old_owner_id))?;
+        format_vars.insert(
+            "old_owner_name".to_string(),
+            old_owner.name.clone().to_pascal_case(),
+        );
+        // how the owner knew the old owner when they met, e.g. "her old friend Tennia"
+        format_vars.insert(
+            "relationship".to_string(),
+            relationship::describe(
+                relationship::relationship(world, owner_id, old_owner_id),
+                record.time,
+                &owner.pronouns.dep_genitive,
+                &old_owner.name.clone().to_pascal_case(),
+            ),
+        );
+        format_vars.insert(
+            "nominative_pronoun1".to_string(),
+            old_owner.pronouns.nominative.clone(),
+        );
+        format_vars.insert(
+            "accusative_pronoun1".to_string(),
+            old_owner.pronouns.accusative.clone(),
+        );
+        format_vars.insert(
+            "dep_genitive_pronoun1".to_string(),
+            old_owner.pronouns.dep_genitive.clone(),
+        );
+    }
+    // add old city info if applicable.
+    if event.event_type == EventType::EventMove {
+        if let Some(previous_record) = previous_record {
+            let old_city_id = previous_record.location()?;
+            let old_city = world
+                .cities
+                .get(&old_city_id)
+                .ok_or(WorldError::MissingCity(old_city_id))?;
+            format_vars.insert(
+                "old_city_name".to_string(),
+                old_city.name_at(record.time).to_pascal_case(),
+            );
+        }
+    }
+    // add what drove everyone out, e.g. "the fire"
+    format_vars.insert(
+        "calamity".to_string(),
+        calamity_at(world, city_id, record.time)
+            .calamity()
+            .name()
+            .to_string(),
+    );
+    // add what a calamity did that year and where, if it did anything
+    if let Some((milestone_city_id, milestone_kind, _)) =
+        calamity_milestone_at(world, record.time, city_id)
+    {
+        let milestone_city = world
+            .cities
+            .get(&milestone_city_id)
+            .ok_or(WorldError::MissingCity(milestone_city_id))?;
+        format_vars.insert(
+            "milestone_city".to_string(),
+            milestone_city.name_at(record.time).to_pascal_case(),
+        );
+        format_vars.insert(
+            "milestone_calamity".to_string(),
+            milestone_kind.calamity().name().to_string(),
+        );
+    }
+    // add year
+    format_vars.insert("year".to_string(), event.start_time.to_string());
+    // format all lines
+    for line in lines {
+        // format line
+        let line_formatted = strfmt(line, &format_vars)?;
+        trace!(target: LOG_NARRATION, "event line: {:?}", line_formatted);
+        lines_formatted.push(line_formatted);
+    }
+
+    // return formatted lines
+    Ok(lines_formatted)
+}
+
+pub fn generate_lines_from_event<R: Rng + ?Sized>(
+    world: &World,
+    record: &ItemMoveRecord,
+    previous_record: Option<&ItemMoveRecord>,
+    descs: &DescJson,
+    rng: &mut R,
+) -> Result<Option<Vec<String>>, NarrationError> {
+    let event_id = record.event_id()?;
+    let event = world
+        .events
+        .get(&event_id)
+        .ok_or(WorldError::MissingEvent(event_id))?;
+    let lines = match event.event_type {
+        EventType::EventCreation(_) => descs
+            .event_lines
+            .creation_lines
+            .choose(rng)
+            .ok_or(NarrationError::MissingLines("creation lines"))?,
+        EventType::EventDeath(cause) => descs
+            .event_lines
+            .death_cause_lines
+            .for_cause(cause)
+            .choose(rng)
+            .or_else(|| descs.event_lines.death_lines.choose(rng))
+            .ok_or(NarrationError::MissingLines("death lines"))?,
+        EventType::EventMove => descs
+            .event_lines
+            .move_lines
+            .choose(rng)
+            .ok_or(NarrationError::MissingLines("move lines"))?,
+        EventType::EventLoss(_) => descs
+            .event_lines
+            .lost_item_lines
+            .choose(rng)
+            .ok_or(NarrationError::MissingLines("lost item lines"))?,
+        EventType::EventEncounter => {
+            let encountered_id = *event
+                .characters
+                .get(1)
+                .ok_or(WorldError::IncompleteRecord("encountered character"))?;
+            let is_postmortem_encounter = world
+                .characters
+                .get(&encountered_id)
+                .ok_or(WorldError::MissingCharacter(encountered_id))?
+                .has_died(world);
+            let const_lines = if is_postmortem_encounter {
+                &descs.event_lines.postmortem_exchange_lines
+            } else {
+                &descs.event_lines.exchange_lines
+            };
+            const_lines
+                .choose(rng)
+                .ok_or(NarrationError::MissingLines("exchange lines"))?
+        }
+        _ => return Ok(None),
+    };
+    let mut lines_formatted = format_event_lines(lines, world, record, previous_record)?;
+
+    // say something about what the owner was like
+    let owner_id = record.new_owner.or(event.characters.first().copied());
+    let strong_traits = owner_id
+        .and_then(|owner_id| world.characters.get(&owner_id))
+        .map_or(Vec::new(), |owner| owner.personality.strong_traits());
+    let personality_lines = strong_traits.choose(rng).and_then(|&personality_trait| {
+        descs
+            .event_lines
+            .personality_lines
+            .for_trait(personality_trait)
+            .choose(rng)
+    });
+    if let Some(personality_lines) = personality_lines.filter(|_| rng.gen_bool(PERSONALITY_CHANCE))
+    {
+        lines_formatted.extend(format_event_lines(
+            personality_lines,
+            world,
+            record,
+            previous_record,
+        )?);
+    }
+
+    // remember what's become of the city since
+    let city_id = record.location()?;
+    let city = world
+        .cities
+        .get(&city_id)
+        .ok_or(WorldError::MissingCity(city_id))?;
+    let city_lines = if city
+        .abandoned
+        .is_some_and(|abandoned| abandoned > record.time)
+    {
+        descs.event_lines.lost_city_lines.choose(rng)
+    } else if city.name_at(record.time) != city.name {
+        descs.event_lines.renamed_city_lines.choose(rng)
+    } else {
+        None
+    };
+    if let Some(city_lines) = city_lines.filter(|_| rng.gen_bool(CITY_FATE_CHANCE)) {
+        lines_formatted.extend(format_event_lines(
+            city_lines,
+            world,
+            record,
+            previous_record,
+        )?);
+    }
+
+    // remember what the calamity was up to that year
+    let milestone_lines = match calamity_milestone_at(world, record.time, city_id) {
+        Some((_, _, milestone)) => match milestone.event_type {
+            EventType::EventCalamityArrival(..) => {
+                descs.event_lines.calamity_arrival_lines.choose(rng)
+            }
+            EventType::EventCityFall(..) => descs.event_lines.city_fell_lines.choose(rng),
+            EventType::EventCalamityRecession(..) => {
+                descs.event_lines.calamity_receded_lines.choose(rng)
+            }
+            _ => None,
+        },
+        None => None,
+    };
+    if let Some(milestone_lines) = milestone_lines.filter(|_| rng.gen_bool(MILESTONE_CHANCE)) {
+        lines_formatted.extend(format_event_lines(
+            milestone_lines,
+            world,
+            record,
+            previous_record,
+        )?);
+    }
+
+    Ok(Some(lines_formatted))
+}
+
+pub fn generate_stories<R: Rng + ?Sized>(
+    world: &World,
+    item_id: ItemID,
+    descs: &DescJson,
+    rng: &mut R,
+) -> Result<Vec<Vec<String>>, NarrationError> {
+    let item = world
+        .items
+        .get(&item_id)
+        .ok_or(WorldError::MissingItem(item_id))?;
+    let mut stories: Vec<Vec<String>> = Vec::new();
+    let records = &item.owner_records;
+    let oldest_record = records.first().ok_or(NarrationError::NoRecords(item_id))?;
+    let newest_record = records.last().ok_or(NarrationError::NoRecords(item_id))?;
+
+    // generate oldest story, special dialogue for this
+    let mut oldest_story_lines: Vec<String> = Vec::new();
+    // add lines for event
+    if let Some(lines) = generate_lines_from_event(world, oldest_record, None, descs, rng)? {
+        oldest_story_lines.extend(lines);
+    }
+    // choose an outro
+    let outro = descs
+        .story_outros
+        .choose(rng)
+        .ok_or(NarrationError::MissingLines("story outros"))?;
+    oldest_story_lines.push(outro.to_string());
+    // push to array of stories
+    stories.push(oldest_story_lines);
+
+    // generate in between stories
+    for pair in records.windows(2).take(records.len().saturating_sub(2)) {
+        let (previous_record, record) = (&pair[0], &pair[1]);
+        if let Some(lines) =
+            generate_lines_from_event(world, record, Some(previous_record), descs, rng)?
+        {
+            stories.push(lines);
+        }
+    }
+
+    // generate newest story, special dialogue for this
+    let mut newest_story_lines: Vec<String> = Vec::new();
+    // choose an intro
+    let intro = descs
+        .story_intros
+        .choose(rng)
+        .ok_or(NarrationError::MissingLines("story intros"))?;
+    newest_story_lines.push(intro.to_string());
+    // add lines for event
+    let previous_record = records.len().checked_sub(2).map(|i| &records[i]);
+    if let Some(lines) =
+        generate_lines_from_event(world, newest_record, previous_record, descs, rng)?
+    {
+        newest_story_lines.extend(lines);
+    }
+    // push to array of stories
+    stories.push(newest_story_lines);
+
+    // collect into array and return
+    Ok(stories)
+}
+
+// -- Whole world narration --
+
+// everything the shopkeeper can say about an item
+pub struct ItemNarration {
+    pub item_id: ItemID,
+    pub item_type: String,
+    pub description: Vec<String>,
+    pub stories: Vec<Vec<String>>,
+}
+
+// narrates every item in the world, in item id order.
+// the narration is seeded from the world's seed, so the same world always gets the same dialogue.
+pub fn narrate_world(
+    world: &World,
+    descs: &DescJson,
+) -> Result<Vec<ItemNarration>, NarrationError> {
+    let mut rng = seeded_rng(world.seed, NARRATION_STREAM);
+    let mut narrations = Vec::new();
+    for (&item_id, item) in &world.items {
+        let item_types = get_item_types(item)?;
+        let description = generate_description(item, item_types, descs, &mut rng)?;
+        let stories = generate_stories(world, item_id, descs, &mut rng)?;
+
+        narrations.push(ItemNarration {
+            item_id,
+            item_type: item.item_type.to_string(),
+            description,
+            stories,
+        });
+    }
+
+    Ok(narrations)
+}
@@ -0,0 +1,221 @@
+use crate::error::WorldError;
+use crate::world::{CharacterID, Personality, TraitLevel, World};
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// -- Relationships --
+
+// what two characters are to each other
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelationshipKind {
+    Acquaintance, // they've met
+    Friend,       // they've met a few times and get on
+    Rival,        // they've met a few times and don't
+    Lover,        // friends who became more
+    Family,       // kin from the start, whether they've met or not
+}
+
+// how one character knows another. both characters keep a copy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Relationship {
+    pub encounters: usize,                     // how many times they've met
+    pub kinds: Vec<(usize, RelationshipKind)>, // what they've been to each other and since when, oldest first
+}
+
+// how many meetings it takes for acquaintances to become friends or rivals
+const CLOSE_AFTER: usize = 2;
+// how many meetings it takes for friends to maybe become lovers
+const LOVERS_AFTER: usize = 4;
+const RIVAL_CHANCE: f64 = 0.25;
+const LOVER_CHANCE: f64 = 0.3;
+// how long friends have to have been friends to be old friends
+const OLD_FRIENDS_AFTER: usize = 10;
+
+impl Relationship {
+    fn new(time: usize, kind: RelationshipKind) -> Self {
+        Relationship {
+            encounters: 0,
+            kinds: vec![(time, kind)],
+        }
+    }
+
+    pub fn kind(&self) -> RelationshipKind {
+        self.kinds
+            .last()
+            .map_or(RelationshipKind::Acquaintance, |&(_, kind)| kind)
+    }
+
+    // what they were to each other at the given time, if they knew each other yet
+    pub fn kind_at(&self, time: usize) -> Option<RelationshipKind> {
+        self.kinds
+            .iter()
+            .take_while(|&&(since, _)| since <= time)
+            .last()
+            .map(|&(_, kind)| kind)
+    }
+
+    // how long they'd been whatever they were at the given time
+    fn known_for(&self, time: usize) -> usize {
+        self.kinds
+            .iter()
+            .take_while(|&&(since, _)| since <= time)
+            .last()
+            .map_or(0, |&(since, _)| time - since)
+    }
+
+    // how much more likely a character is to seek out someone they know like this
+    pub fn encounter_factor(&self) -> usize {
+        match self.kind() {
+            RelationshipKind::Acquaintance => 2,
+            RelationshipKind::Friend => 3,
+            RelationshipKind::Rival => 1,
+            RelationshipKind::Lover | RelationshipKind::Family => 4,
+        }
+    }
+
+    // how much more readily a character gives things to someone they know like this
+    pub fn giving_factor(&self) -> f32 {
+        match self.kind() {
+            RelationshipKind::Acquaintance => 1.0,
+            RelationshipKind::Friend => 1.5,
+            RelationshipKind::Rival => 0.5,
+            RelationshipKind::Lover | RelationshipKind::Family => 2.0,
+        }
+    }
+}
+
+// whether two people who keep running into each other come to like each other
+fn gets_on<R: Rng + ?Sized>(first: &Personality, second: &Personality, rng: &mut R) -> bool {
+    let greedy = [first, second]
+        .iter()
+        .filter(|personality| personality.greed == TraitLevel::High)
+        .count();
+    let rival_chance = RIVAL_CHANCE * (greedy + 1) as f64;
+    !rng.gen_bool(rival_chance.min(1.0))
+}
+
+// e.g. "her old friend Tennia", for narration. someone they didn't know yet is a stranger
+pub fn describe(
+    relationship: Option<&Relationship>,
+    time: usize,
+    genitive: &str,
+    name: &str,
+) -> String {
+    let relationship = match relationship {
+        Some(relationship) => relationship,
+        None => return format!("a stranger named {}", name),
+    };
+    match relationship.kind_at(time) {
+        None => format!("a stranger named {}", name),
+        Some(RelationshipKind::Acquaintance) => format!("{} acquaintance {}", genitive, name),
+        Some(RelationshipKind::Friend) if relationship.known_for(time) >= OLD_FRIENDS_AFTER => {
+            format!("{} old friend {}", genitive, name)
+        }
+        Some(RelationshipKind::Friend) => format!("{} friend {}", genitive, name),
+        Some(RelationshipKind::Rival) => format!("{} rival {}", genitive, name),
+        Some(RelationshipKind::Lover) => format!("{} beloved {}", genitive, name),
+        Some(RelationshipKind::Family) => format!("{} kin {}", genitive, name),
+    }
+}
+
+// how one character knows another, if they do
+pub fn relationship(world: &World, from: CharacterID, to: CharacterID) -> Option<&Relationship> {
+    world.characters.get(&from)?.relationships.get(&to)
+}
+
+// sets what two characters are to each other from now on, for both of them
+pub(crate) fn relate(
+    world: &mut World,
+    first: CharacterID,
+    second: CharacterID,
+    time: usize,
+    kind: RelationshipKind,
+) -> Result<(), WorldError> {
+    for (from, to) in [(first, second), (second, first)] {
+        let relationships = &mut world
+            .characters
+            .get_mut(&from)
+            .ok_or(WorldError::MissingCharacter(from))?
+            .relationships;
+        match relationships.get_mut(&to) {
+            Some(relationship) => relationship.kinds.push((time, kind)),
+            None => {
+                relationships.insert(to, Relationship::new(time, kind));
+            }
+        }
+    }
+    Ok(())
+}
+
+// some characters start out as kin to someone generated before them
+pub fn generate_families<R: Rng + ?Sized>(
+    world: &mut World,
+    rng: &mut R,
+) -> Result<(), WorldError> {
+    let character_ids: Vec<CharacterID> = world.characters.keys().copied().collect();
+    for (i, &character) in character_ids.iter().enumerate() {
+        if !rng.gen_bool(world.config.family_chance) {
+            continue;
+        }
+        if let Some(&relative) = character_ids[..i].iter().choose(rng) {
+            relate(world, character, relative, 0, RelationshipKind::Family)?;
+        }
+    }
+    Ok(())
+}
+
+// two characters met. strangers become acquaintances, and people who keep meeting
+// become friends or rivals, and friends sometimes become lovers
+pub fn meet<R: Rng + ?Sized>(
+    world: &mut World,
+    first: CharacterID,
+    second: CharacterID,
+    time: usize,
+    rng: &mut R,
+) -> Result<(), WorldError> {
+    let relationship = match relationship(world, first, second) {
+        Some(relationship) => relationship.clone(),
+        None => {
+            relate(world, first, second, time, RelationshipKind::Acquaintance)?;
+            Relationship::new(time, RelationshipKind::Acquaintance)
+        }
+    };
+    let encounters = relationship.encounters + 1;
+    for (from, to) in [(first, second), (second, first)] {
+        if let Some(relationship) = world
+            .characters
+            .get_mut(&from)
+            .and_then(|character| character.relationships.get_mut(&to))
+        {
+            relationship.encounters = encounters;
+        }
+    }
+
+    let personality = |id: CharacterID| {
+        world
+            .characters
+            .get(&id)
+            .map(|character| character.personality)
+            .ok_or(WorldError::MissingCharacter(id))
+    };
+    let (first_personality, second_personality) = (personality(first)?, personality(second)?);
+    let next_kind = match relationship.kind() {
+        RelationshipKind::Acquaintance if encounters >= CLOSE_AFTER => {
+            if gets_on(&first_personality, &second_personality, rng) {
+                Some(RelationshipKind::Friend)
+            } else {
+                Some(RelationshipKind::Rival)
+            }
+        }
+        RelationshipKind::Friend if encounters >= LOVERS_AFTER && rng.gen_bool(LOVER_CHANCE) => {
+            Some(RelationshipKind::Lover)
+        }
+        _ => None,
+    };
+    if let Some(next_kind) = next_kind {
+        relate(world, first, second, time, next_kind)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::calamity::Calamities;
+use crate::world::{City, CityID, World};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::BTreeSet;
+
+// -- Fleeing --
+
+// how much worse than a road of length 1 it is to go through a city the calamity is in
+const DANGER_COST: usize = 10;
+// how much worse than a road of length 1 it is to go through a city nobody lives in any more
+const ABANDONED_COST: usize = 3;
+
+// the cities near a character that they know the calamity has got to.
+// word only travels so many roads, so nobody knows everything the calamity is doing
+pub fn known_calamity(world: &World, calamities: &Calamities, city_id: CityID) -> BTreeSet<CityID> {
+    world
+        .roads
+        .nearby(city_id, world.config.perception_range)
+        .into_iter()
+        .filter(|&city_id| calamities.is_in(city_id))
+        .collect()
+}
+
+// whether a city is somewhere worth fleeing to, as far as a character knows
+pub fn is_safe(world: &World, known_calamity: &BTreeSet<CityID>, city_id: CityID) -> bool {
+    !known_calamity.contains(&city_id) && world.cities.get(&city_id).is_some_and(City::is_standing)
+}
+
+// picks somewhere safe for a character to flee to. they try to get ahead of the calamity, past the
+// furthest they know it's got, and settle for anywhere further down the road if they can't
+pub fn choose_haven<R: Rng + ?Sized>(
+    world: &World,
+    known_calamity: &BTreeSet<CityID>,
+    from: CityID,
+    rng: &mut R,
+) -> Option<CityID> {
+    let layer = world.roads.layer_of(from)?;
+    let front = known_calamity
+        .iter()
+        .filter_map(|&city_id| world.roads.layer_of(city_id))
+        .max()
+        .map_or(layer, |front| front.max(layer));
+
+    let safe: Vec<(CityID, usize)> = world
+        .roads
+        .reachable_from(from)
+        .into_iter()
+        .filter(|&city_id| is_safe(world, known_calamity, city_id))
+        .filter_map(|city_id| Some((city_id, world.roads.layer_of(city_id)?)))
+        .collect();
+    let ahead: Vec<CityID> = safe
+        .iter()
+        .filter(|&&(_, haven_layer)| haven_layer > front)
+        .map(|&(city_id, _)| city_id)
+        .collect();
+    let onward: Vec<CityID> = safe
+        .iter()
+        .filter(|&&(_, haven_layer)| haven_layer > layer)
+        .map(|&(city_id, _)| city_id)
+        .collect();
+
+    if ahead.is_empty() {
+        onward.choose(rng).copied()
+    } else {
+        ahead.choose(rng).copied()
+    }
+}
+
+// the way to a city that keeps out of the calamity's way as far as a character knows,
+// and out of abandoned cities where it can. includes both ends
+pub fn plan_route(
+    world: &World,
+    known_calamity: &BTreeSet<CityID>,
+    from: CityID,
+    to: CityID,
+) -> Option<Vec<CityID>> {
+    world.roads.cheapest_path(
+        from,
+        |city_id| city_id == to,
+        |road, next| {
+            let danger = if known_calamity.contains(&next) {
+                DANGER_COST
+            } else if !world.cities.get(&next).is_some_and(City::is_standing) {
+                ABANDONED_COST
+            } else {
+                0
+            };
+            road.length + danger
+        },
+    )
+}
@@ -0,0 +1,268 @@
+use crate::calamity::CalamityKind;
+use crate::config::WorldConfig;
+use crate::error::SaveError;
+use crate::graph::RoadGraph;
+use crate::map::layout_cities;
+use crate::world::World;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+// -- Save format --
+
+// the version written by save_world. bump this whenever a change to the world types would stop old saves
+// from deserializing, and add a migration that upgrades the previous version below.
+pub const SAVE_VERSION: u32 = 5;
+
+// migrations on the raw json, where MIGRATIONS[n] upgrades a version n save to version n + 1
+const MIGRATIONS: [fn(Value) -> Result<Value, SaveError>; SAVE_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+// everything in a save file. the config and seed are kept next to the world so a save can be inspected
+// or regenerated without deserializing the whole history.
+#[derive(Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub config: WorldConfig,
+    pub seed: u64,
+    pub world: World,
+}
+
+// the same as SaveFile, but borrowing the world so it doesn't have to be cloned to be saved
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u32,
+    config: &'a WorldConfig,
+    seed: u64,
+    world: &'a World,
+}
+
+// -- Migrations --
+
+// version 0 saves are a bare world with no envelope
+fn migrate_v0_to_v1(world: Value) -> Result<Value, SaveError> {
+    let config = world
+        .get("config")
+        .cloned()
+        .ok_or(SaveError::Invalid("world has no config"))?;
+    let seed = world
+        .get("seed")
+        .cloned()
+        .ok_or(SaveError::Invalid("world has no seed"))?;
+    Ok(json!({
+        "version": 1,
+        "config": config,
+        "seed": seed,
+        "world": world,
+    }))
+}
+
+// version 1 saves kept the layers on the world and one way neighbour lists on each city,
+// which are now the world's road graph
+fn migrate_v1_to_v2(mut save: Value) -> Result<Value, SaveError> {
+    let world = save
+        .get_mut("world")
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("save has no world"))?;
+    let layers = world
+        .remove("layers")
+        .ok_or(SaveError::Invalid("world has no layers"))?;
+    let cities = world
+        .get_mut("cities")
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("world has no cities"))?;
+
+    // json object keys are sorted as strings, so put the cities back in id order
+    let mut city_ids: Vec<(u64, String)> = cities
+        .keys()
+        .map(|key| key.parse().map(|id| (id, key.clone())))
+        .collect::<Result<_, _>>()
+        .map_err(|_| SaveError::Invalid("city id is not a number"))?;
+    city_ids.sort();
+
+    let mut roads = Vec::new();
+    for (id, key) in city_ids {
+        let city = cities
+            .get_mut(&key)
+            .and_then(Value::as_object_mut)
+            .ok_or(SaveError::Invalid("city is not an object"))?;
+        let neighbours = city
+            .remove("neighbours")
+            .and_then(|neighbours| neighbours.as_array().cloned())
+            .ok_or(SaveError::Invalid("city has no neighbours"))?;
+        for neighbour in neighbours {
+            roads.push(json!({
+                "from": id,
+                "to": neighbour,
+                "direction": "OneWay",
+                "length": 1,
+            }));
+        }
+    }
+
+    world.insert(
+        "roads".to_string(),
+        json!({ "layers": layers, "roads": roads }),
+    );
+    save["version"] = json!(2);
+    Ok(save)
+}
+
+// version 2 saves had no city positions, so lay the cities out from the road graph
+fn migrate_v2_to_v3(mut save: Value) -> Result<Value, SaveError> {
+    let world = save
+        .get_mut("world")
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("save has no world"))?;
+    let roads: RoadGraph = world
+        .get("roads")
+        .cloned()
+        .ok_or(SaveError::Invalid("world has no roads"))
+        .and_then(|roads| serde_json::from_value(roads).map_err(SaveError::Json))?;
+    let cities = world
+        .get_mut("cities")
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("world has no cities"))?;
+
+    for (city_id, position) in layout_cities(&roads) {
+        let city = cities
+            .get_mut(&city_id.0.to_string())
+            .and_then(Value::as_object_mut)
+            .ok_or(SaveError::Invalid(
+                "road graph has a city that isn't in the world",
+            ))?;
+        city.insert("position".to_string(), json!(position));
+    }
+
+    save["version"] = json!(3);
+    Ok(save)
+}
+
+// version 3 saves recorded calamity milestones with just the city.
+// there was only ever the one calamity then, the one in the config
+fn migrate_v3_to_v4(mut save: Value) -> Result<Value, SaveError> {
+    let kind = match save.get("config").and_then(|config| config.get("calamity")) {
+        Some(kind) => kind.clone(),
+        None => json!(CalamityKind::default()),
+    };
+    let events = save
+        .get_mut("world")
+        .and_then(|world| world.get_mut("events"))
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("world has no events"))?;
+
+    for event in events.values_mut() {
+        let event_type = match event.get_mut("event_type").and_then(Value::as_object_mut) {
+            Some(event_type) => event_type,
+            None => continue, // unit variants are plain strings, and never need migrating
+        };
+        for variant in [
+            "EventCalamityArrival",
+            "EventCityFall",
+            "EventCalamityRecession",
+        ] {
+            if let Some(city) = event_type.get_mut(variant).filter(|city| city.is_u64()) {
+                *city = json!([city.clone(), kind.clone()]);
+            }
+        }
+    }
+
+    save["version"] = json!(4);
+    Ok(save)
+}
+
+// version 4 saves recorded deaths without a cause, and mostly as moves by mistake.
+// every death back then was to the calamity
+fn migrate_v4_to_v5(mut save: Value) -> Result<Value, SaveError> {
+    let events = save
+        .get_mut("world")
+        .and_then(|world| world.get_mut("events"))
+        .and_then(Value::as_object_mut)
+        .ok_or(SaveError::Invalid("world has no events"))?;
+
+    for event in events.values_mut() {
+        let event = event
+            .as_object_mut()
+            .ok_or(SaveError::Invalid("event is not an object"))?;
+        let was_death = match event.get("event_type").and_then(Value::as_str) {
+            Some("EventDeath") => true,
+            Some("EventMove") => event
+                .get("summary")
+                .and_then(Value::as_str)
+                .is_some_and(|summary| summary.contains(" died in ")),
+            _ => false,
+        };
+        if was_death {
+            event.insert(
+                "event_type".to_string(),
+                json!({ "EventDeath": "Calamity" }),
+            );
+        }
+    }
+
+    save["version"] = json!(5);
+    Ok(save)
+}
+
+// works out which version a save was written with
+fn save_version(save: &Value) -> Result<u32, SaveError> {
+    match save.get("version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(SaveError::Invalid("version is not a number")),
+        // saves from before the envelope was added are just the world
+        None if save.get("cities").is_some() => Ok(0),
+        None => Err(SaveError::Invalid("not a history save file")),
+    }
+}
+
+// upgrades a save of any older version to the current version
+pub fn migrate(mut save: Value) -> Result<Value, SaveError> {
+    let version = save_version(&save)?;
+    if version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        save = migration(save)?;
+    }
+    Ok(save)
+}
+
+// -- Saving and loading --
+
+// writes a generated world, including its events and item records, to a json file
+pub fn save_world(world: &World, path: impl AsRef<Path>) -> Result<(), SaveError> {
+    let save = SaveFileRef {
+        version: SAVE_VERSION,
+        config: &world.config,
+        seed: world.seed,
+        world,
+    };
+    let file = File::create(path).map_err(SaveError::Io)?;
+    serde_json::to_writer(BufWriter::new(file), &save).map_err(SaveError::Json)
+}
+
+// parses a save of any supported version, migrating it to the current version first
+pub fn parse_save(json: &str) -> Result<SaveFile, SaveError> {
+    let save: Value = serde_json::from_str(json).map_err(SaveError::Json)?;
+    serde_json::from_value(migrate(save)?).map_err(SaveError::Json)
+}
+
+pub fn load_save(path: impl AsRef<Path>) -> Result<SaveFile, SaveError> {
+    let json = fs::read_to_string(path).map_err(SaveError::Io)?;
+    parse_save(&json)
+}
+
+// reads back a world written by save_world
+pub fn load_world(path: impl AsRef<Path>) -> Result<World, SaveError> {
+    Ok(load_save(path)?.world)
+}
@@ -1,261 +1,290 @@
+use crate::calamity::{self, Calamities, CalamityKind, CalamityMilestone};
+use crate::config::WorldConfig;
+use crate::error::WorldError;
+use crate::graph::{RoadDirection, RoadGraph};
+use crate::map::{layout_cities, position_in_layer, stretch_roads, travel_time};
+use crate::relationship::{self, Relationship, RelationshipKind};
+use crate::route;
+use log::{debug, info, trace};
 use rand::distributions::WeightedIndex;
 use rand::prelude::Distribution;
-use rand::rngs::ThreadRng;
 use rand::seq::{IteratorRandom, SliceRandom};
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct CityID(pub usize);
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct EventID(pub usize);
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct CharacterID(pub usize);
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct ItemID(pub usize);
 
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Year(isize);
 
 // -- Constants --
 
-pub const MAX_TIME: usize = 9;
-const NUM_LAYERS: usize = 5;
-const MIN_CITIES_IN_LAYER: usize = 1;
-const MAX_CITIES_IN_LAYER: usize = 2;
-const NUM_CHARACTERS: usize = 6; // number of characters to generate
-const NUM_ITEMS: usize = 6;
-
 const NUM_EVENTS: usize = 4; // number of event types
 const LIST_EVENTS: [EventType; NUM_EVENTS] = [
     EventType::EventMove,
-    EventType::EventDeath,
+    EventType::EventDeath(DeathCause::Calamity), // the only deaths characters choose between are to the calamity
     EventType::EventEncounter,
     EventType::EventIdle,
 ];
 
-const CALAMITY_FREQ: usize = 5; // the frequency with which the calamity advances to the next layer
-const CALAMITY_DEADLINESS: usize = 2; // calamity's kill probability increases with respect to this every time step
-const ENCOUNTER_POW: u32 = 2; // encounter chance is determined by the city population to the power of this constant
+// -- Logging --
 
-const PROB_ITEM_PASSED: f32 = 1.0;
+// log targets, so that each part of generation can be filtered separately
+pub const LOG_WORLDGEN: &str = "worldgen";
+pub const LOG_CALAMITY: &str = "calamity";
+pub const LOG_EVENTS: &str = "events";
+pub const LOG_NARRATION: &str = "narration";
 
-// -- World and world gen --
+// -- Randomness --
 
-// the state of a character at some given time
-pub struct CharacterState {
-    character: CharacterID,
-    city: CityID,
-    event_probability_map: WeightedIndex<usize>,
-    items: Vec<ItemID>,
-    dead: bool,
-    encountered: bool,
-}
+// every random choice made while generating a world comes from a WorldRng seeded from the world's seed.
+// each stage of generation draws from its own stream so that e.g. changing the narration doesn't change the events.
+pub type WorldRng = ChaCha8Rng;
+
+pub const WORLDGEN_STREAM: u64 = 0;
+pub const EVENTGEN_STREAM: u64 = 1;
+pub const NARRATION_STREAM: u64 = 2;
 
-// the state of the calamity
-pub struct CalamityState {
-    pub city_states: HashMap<CityID, usize>, // how long each city has been "in calamity"
-    pub freq: usize, // the frequency with which the calamity moves (once every freq time units)
-    pub calamity_layer_i: Option<usize>, // which layer the calamity is currently at
+pub fn seeded_rng(seed: u64, stream: u64) -> WorldRng {
+    let mut rng = WorldRng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng
 }
 
-impl CalamityState {
-    pub fn new(cities: Vec<&CityID>) -> Self {
-        let mut state = CalamityState {
-            city_states: HashMap::new(),
-            freq: CALAMITY_FREQ,
-            calamity_layer_i: None,
-        };
+// -- World and world gen --
 
-        for &city in cities {
-            state.city_states.insert(city, 0);
-        }
+// the state of a character at some given time
+pub struct CharacterState {
+    pub(crate) character: CharacterID,
+    pub(crate) city: CityID, // while travelling, the city the character left
+    pub(crate) event_probability_map: WeightedIndex<usize>,
+    pub(crate) items: Vec<ItemID>,
+    pub(crate) dead: bool,
+    pub(crate) encountered: bool,
+    pub(crate) journey: Option<Journey>, // the road the character is on, if they're travelling
+    pub(crate) heading_for: Option<CityID>, // where the character is fleeing to, if they've decided
+    pub(crate) personality: Personality,
+}
 
-        state
+impl CharacterState {
+    // a character settled in a city with nothing on them, among this many characters in the world
+    fn new(
+        character: CharacterID,
+        city: CityID,
+        personality: Personality,
+        num_characters: usize,
+    ) -> Result<Self, WorldError> {
+        let starting_weights: [usize; NUM_EVENTS] = [
+            personality.move_weight(cmp::max(num_characters / 2, 1)), // EventMove
+            0,                                                        // EventDeath
+            personality.encounter_weight(num_characters), // EventEncounter (keep this proportional to the number of other characters in the same city)
+            personality.idle_weight(2),                   // EventIdle
+        ];
+        Ok(CharacterState {
+            character,
+            city,
+            items: Vec::new(), // starting inventory is empty
+            event_probability_map: WeightedIndex::new(starting_weights)?,
+            dead: false,
+            encountered: false,
+            journey: None,
+            heading_for: None,
+            personality,
+        })
     }
 
-    pub fn calamity_step(
-        &mut self,
-        time: usize,
-        layers: &Vec<Vec<CityID>>,
-        character_states: &mut Vec<CharacterState>,
-        city_populations: &HashMap<CityID, Vec<CharacterID>>,
-    ) {
-        if time % self.freq == 0 && time != 0 {
-            // increase layer of calamity, if calamity isnt present yet, put it on layer 0
-            match self.calamity_layer_i {
-                Some(lyr) => {
-                    if lyr < NUM_LAYERS - 1 {
-                        self.calamity_layer_i = Some(lyr + 1);
-                    }
-                }
-                None => {
-                    self.calamity_layer_i = Some(0);
-                }
-            }
+    // after meeting someone, a character doesn't meet anyone else until they move on
+    fn stop_encountering(&mut self) -> Result<(), WorldError> {
+        self.encountered = true;
+        trace!(
+            target: LOG_EVENTS,
+            "event encounter: new encounter weight for char {:?}: {:?}",
+            self.character,
+            0
+        );
+        if self
+            .event_probability_map
+            .update_weights(&[(2, &0)])
+            .is_err()
+        {
+            // if no actions are possible, character will stop forever
+            self.event_probability_map = WeightedIndex::new([0, 0, 0, 1])?;
         }
+        Ok(())
+    }
+}
 
-        // increase calamity time of each city in calamity
-        match self.calamity_layer_i {
-            Some(calamity_layer_i_num) => {
-                for layer_i in 0..=calamity_layer_i_num {
-                    let layer = &layers[layer_i];
-                    for &city_id in layer {
-                        let prev_city_state = self.city_states.get(&city_id).unwrap();
-                        self.city_states.insert(city_id, prev_city_state + 1);
-                    }
-                }
-            }
-            None => (),
-        }
+// a character on the road between two cities
+#[derive(Clone, Copy, Debug)]
+pub struct Journey {
+    pub from: CityID,
+    pub to: CityID,
+    pub arrival_time: usize,
+    pub event: EventID, // the move event for this journey
+}
 
-        // update death probabilities of each character in calamity
-        match self.calamity_layer_i {
-            Some(calamity_layer_i_num) => {
-                for layer_i in 0..=calamity_layer_i_num {
-                    let layer = &layers[layer_i];
-                    for &city_id in layer {
-                        let city_population = city_populations.get(&city_id).unwrap();
-                        let &city_state = self.city_states.get(&city_id).unwrap();
-                        for &CharacterID(id) in city_population {
-                            let char_state = &character_states[id];
-                            // dont update states if character is already dead
-                            if !char_state.dead {
-                                let last_city_id: usize = 0;
-                                let curr_city_id_num = match city_id {
-                                    CityID(id) => id,
-                                };
-                                let city_pop_without_self = city_population.len() - 1;
-                                let new_move_prob = if curr_city_id_num == last_city_id {
-                                    0
-                                } else {
-                                    city_pop_without_self / 4 + city_state * CALAMITY_DEADLINESS
-                                };
-                                let new_death_prob = city_state * CALAMITY_DEADLINESS;
-                                println!("next death prob for char {:?}: {:?}", id, new_death_prob);
-                                let weight_update = [(0, &new_move_prob), (1, &new_death_prob)];
-                                let update_result = character_states[id]
-                                    .event_probability_map
-                                    .update_weights(&weight_update);
-                                match update_result {
-                                    Ok(_) => (),
-                                    Err(_) => println!("\t\t\tERR: Could not change character {:?}'s death probability", CharacterID(id))
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            None => (),
-        }
+impl Journey {
+    // whether two journeys are on the same road, in either direction
+    pub fn shares_road_with(&self, other: &Journey) -> bool {
+        (self.from == other.from && self.to == other.to)
+            || (self.from == other.to && self.to == other.from)
     }
 }
 
+// chance per tick that two travellers on the same road meet
+const ROAD_ENCOUNTER_CHANCE: f64 = 0.5;
+
+#[derive(Serialize, Deserialize)]
 pub struct World {
-    pub cities: HashMap<CityID, City>,
-    pub characters: HashMap<CharacterID, Character>,
-    pub events: HashMap<EventID, Event>,
-    pub items: HashMap<ItemID, Item>,
+    pub seed: u64, // the seed the world was generated from
+    pub config: WorldConfig,
+    pub cities: BTreeMap<CityID, City>,
+    pub characters: BTreeMap<CharacterID, Character>,
+    pub events: BTreeMap<EventID, Event>,
+    pub items: BTreeMap<ItemID, Item>,
     pub city_id_counter: usize,
     pub event_id_counter: usize,
     character_id_counter: usize,
     item_id_counter: usize,
-    pub layers: [Vec<CityID>; NUM_LAYERS],
+    pub roads: RoadGraph,
 }
 
 impl World {
-    pub fn new() -> Self {
+    pub fn new(config: WorldConfig) -> Self {
         World {
-            cities: HashMap::new(),
-            characters: HashMap::new(),
-            events: HashMap::new(),
-            items: HashMap::new(),
+            seed: 0,
+            roads: RoadGraph::new(config.num_layers),
+            config,
+            cities: BTreeMap::new(),
+            characters: BTreeMap::new(),
+            events: BTreeMap::new(),
+            items: BTreeMap::new(),
             city_id_counter: 0,
             character_id_counter: 0,
             event_id_counter: 0,
             item_id_counter: 0,
-            layers: Default::default(),
         }
     }
 
-    pub fn generate_world() -> Self {
-        let mut world = World::new();
-        println!("---- World Generation ----");
-
-        println!("Generating cities...");
-
-        // add end city
-        world.layers[NUM_LAYERS - 1] = vec![world.add_city(NUM_LAYERS - 1)];
-
-        // add in between cities - we will work from end to beginning so that each city is guaranteed to be connected to at least one city from the next layer
-        for layer in (1..=NUM_LAYERS - 2).rev() {
-            let num_cities =
-                rand::thread_rng().gen_range(MIN_CITIES_IN_LAYER..=MAX_CITIES_IN_LAYER);
-
-            for _ in 1..=num_cities {
-                // initialise new city
-                let new_city = world.add_city(layer);
-                world.layers[layer].push(new_city);
+    // generates a world from a random seed. the seed is kept in world.seed so the world can be regenerated later
+    pub fn generate_world(config: WorldConfig) -> Result<Self, WorldError> {
+        Self::generate_world_with_seed(config, thread_rng().gen())
+    }
 
-                // randomly connect to other cities in next layer
-                let num_in_next_layer = world.layers[layer + 1].len();
-                let num_connections = rand::thread_rng().gen_range(1..=num_in_next_layer);
-                let cities_in_next_layer = world.layers[layer + 1].clone();
-                let cities_to_connect =
-                    cities_in_next_layer.choose_multiple(&mut rand::thread_rng(), num_connections);
+    // generates the same world (and, through generate_events, the same history) every time for a given seed and config
+    pub fn generate_world_with_seed(config: WorldConfig, seed: u64) -> Result<Self, WorldError> {
+        let mut rng = seeded_rng(seed, WORLDGEN_STREAM);
+        let mut world = Self::generate_world_with_rng(config, &mut rng)?;
+        world.seed = seed;
+        Ok(world)
+    }
 
-                for city_id in cities_to_connect {
-                    world.connect_cities(&new_city, &city_id);
-                }
-            }
+    pub fn generate_world_with_rng<R: Rng + ?Sized>(
+        config: WorldConfig,
+        rng: &mut R,
+    ) -> Result<Self, WorldError> {
+        config.validate()?;
+        let mut world = World::new(config);
+        info!(target: LOG_WORLDGEN, "generating cities...");
+
+        let map = world.config.map;
+        map.generate(&mut world, rng)?;
+        // roads take as long to travel as they are long on the map
+        let positions = layout_cities(&world.roads);
+        stretch_roads(&mut world.roads, &positions);
+        for (city_id, position) in positions {
+            world
+                .cities
+                .get_mut(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?
+                .position = position;
         }
 
-        // add start city
-        let start_city = world.add_city(0);
-        world.layers[0] = vec![start_city];
+        info!(target: LOG_WORLDGEN, "generated {:?} cities", world.city_id_counter);
 
-        for city_id in world.layers[1].clone() {
-            world.connect_cities(&start_city, &city_id);
+        // add characters, grown up and of all ages
+        for _ in 0..world.config.num_characters {
+            let character_id = world.add_character(rng);
+            let lifespan = Character::lifespan_gen(world.config.lifespan, rng);
+            let age = rng.gen_range(world.config.come_of_age.min(lifespan - 1)..lifespan);
+            let character = world
+                .characters
+                .get_mut(&character_id)
+                .ok_or(WorldError::MissingCharacter(character_id))?;
+            character.lifespan = lifespan;
+            character.born = -(age as isize);
         }
 
-        println!("Generated {:?} cities", world.city_id_counter);
-
-        // add characters
-        for _ in 0..NUM_CHARACTERS {
-            world.add_character();
+        // give each city its own character
+        for city in world.cities.values_mut() {
+            city.traits = CityTraits::new(rng);
         }
 
-        world
+        // some characters are family
+        relationship::generate_families(&mut world, rng)?;
+
+        Ok(world)
     }
 
-    fn add_city(&mut self, _layer: usize) -> CityID {
+    pub(crate) fn add_city<R: Rng + ?Sized>(&mut self, layer: usize, rng: &mut R) -> CityID {
         let id = self.city_id_counter;
         self.city_id_counter += 1;
 
-        let name = City::name_gen();
+        let name = City::name_gen(rng);
 
         let city = City::new(name);
         self.cities.insert(CityID(id), city);
+        self.roads.add_city(CityID(id), layer);
 
         CityID(id)
     }
 
-    fn connect_cities(&mut self, id1: &CityID, id2: &CityID) {
-        let city1 = self.cities.get_mut(id1).unwrap();
-        city1.neighbours.push(*id2);
+    // how far apart two cities are on the map, as the crow flies
+    pub fn distance_between(&self, id1: CityID, id2: CityID) -> Result<f32, WorldError> {
+        let city1 = self.cities.get(&id1).ok_or(WorldError::MissingCity(id1))?;
+        let city2 = self.cities.get(&id2).ok_or(WorldError::MissingCity(id2))?;
+        Ok(city1.position.distance_to(&city2.position))
+    }
+
+    // connects two cities with a two way road
+    pub(crate) fn connect_cities(&mut self, id1: &CityID, id2: &CityID) -> Result<(), WorldError> {
+        self.add_road(*id1, *id2, RoadDirection::TwoWay, 1)
+    }
+
+    pub(crate) fn add_road(
+        &mut self,
+        from: CityID,
+        to: CityID,
+        direction: RoadDirection,
+        length: usize,
+    ) -> Result<(), WorldError> {
+        for id in [from, to] {
+            if !self.cities.contains_key(&id) {
+                return Err(WorldError::MissingCity(id));
+            }
+        }
+        self.roads.add_road(from, to, direction, length);
 
-        //let mut city2 = self.cities.get_mut(id2).unwrap();
-        // city2.neighbours.push(*id1);
+        Ok(())
     }
 
-    fn add_character(&mut self) -> CharacterID {
+    fn add_character<R: Rng + ?Sized>(&mut self, rng: &mut R) -> CharacterID {
         let id = self.character_id_counter;
         self.character_id_counter += 1;
-        let char = Character::new();
+        let char = Character::new(rng);
         self.characters.insert(CharacterID(id), char);
         CharacterID(id)
     }
@@ -268,25 +297,31 @@ impl World {
         event_type: EventType,
         event_place: CityID,
         summary: String,
-    ) -> EventID {
+    ) -> Result<EventID, WorldError> {
         let event_id = EventID(self.event_id_counter);
         self.event_id_counter += 1;
         let event = Event::new(characters, start_time, end_time, event_type, summary);
-        println!("summary: {:?}", event.summary);
+        debug!(target: LOG_EVENTS, "{}", event.summary);
         for char_id in event.characters.iter() {
             // add event to related characters' list of events
-            let character = self.characters.get_mut(char_id).unwrap();
+            let character = self
+                .characters
+                .get_mut(char_id)
+                .ok_or(WorldError::MissingCharacter(*char_id))?;
             character.events.push(event_id);
-
-            // add event to city events
-            let city = self.cities.get_mut(&event_place).unwrap();
-            city.events.push(event_id);
         }
 
+        // add event to city events
+        let city = self
+            .cities
+            .get_mut(&event_place)
+            .ok_or(WorldError::MissingCity(event_place))?;
+        city.events.push(event_id);
+
         // add event to event map
         self.events.insert(event_id, event);
 
-        event_id
+        Ok(event_id)
     }
 
     fn add_item(
@@ -295,7 +330,7 @@ impl World {
         time: usize,
         initial_owner: CharacterID,
         initial_location: CityID,
-    ) -> ItemID {
+    ) -> Result<ItemID, WorldError> {
         let item_id = ItemID(self.item_id_counter);
         self.item_id_counter += 1;
 
@@ -309,7 +344,7 @@ impl World {
                 "Character #{:?} created Item #{:?} in City #{:?}",
                 initial_owner, item_id, initial_location
             ),
-        );
+        )?;
 
         let item = Item::new(
             item_type,
@@ -319,113 +354,217 @@ impl World {
             creation_event,
         );
         self.items.insert(item_id, item);
-        item_id
+        Ok(item_id)
     }
 
-    fn event_move(
+    // how likely a character is to meet someone in a city with this many other people in it
+    fn encounter_weight(
+        &self,
+        city_id: CityID,
+        population: usize,
+        personality: &Personality,
+    ) -> Result<usize, WorldError> {
+        let city = self
+            .cities
+            .get(&city_id)
+            .ok_or(WorldError::MissingCity(city_id))?;
+        Ok(personality.encounter_weight(
+            population.pow(self.config.encounter_pow) * city.traits.encounter_factor(),
+        ))
+    }
+
+    // sets off along the road to a random onward city. the character arrives once they've travelled the road's length
+    fn event_move<R: Rng + ?Sized>(
         &mut self,
         time: usize,
         state: &mut CharacterState,
-        rng: &mut ThreadRng,
-        city_populations: &HashMap<CityID, Vec<CharacterID>>,
-        city_calamity_states: &HashMap<CityID, usize>,
-    ) {
-        // determine city to move to
-        let curr_city = self.cities.get(&state.city).unwrap();
-        let &next_city = curr_city.neighbours.choose(rng).unwrap();
+        calamities: &Calamities,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        if !self.cities.contains_key(&state.city) {
+            return Err(WorldError::MissingCity(state.city));
+        }
+
+        // decide where to flee to, or change their mind if it's not safe there any more
+        let known_calamity = route::known_calamity(self, calamities, state.city);
+        state.heading_for = state
+            .heading_for
+            .filter(|&haven| haven != state.city && route::is_safe(self, &known_calamity, haven))
+            .or_else(|| route::choose_haven(self, &known_calamity, state.city, rng));
+
+        // head down the road that keeps them furthest from the calamity on the way there
+        let planned_city = state
+            .heading_for
+            .and_then(|haven| route::plan_route(self, &known_calamity, state.city, haven))
+            .and_then(|route| route.get(1).copied());
+        let next_city = match planned_city {
+            Some(city) => city,
+            None => {
+                // with nowhere safe in mind, just keep going onwards, away from the calamity.
+                // nobody heads for a city that's been abandoned, unless there's nowhere else to go
+                let onward = self.roads.onward(state.city);
+                let standing = self.standing_cities(&onward);
+                let destinations = if standing.is_empty() {
+                    onward
+                } else {
+                    standing
+                };
+                match destinations.choose(rng) {
+                    Some(&city) => city,
+                    None => return Ok(()), // nowhere to go from here
+                }
+            }
+        };
+        let travel_time = self
+            .roads
+            .road_between(state.city, next_city)
+            .map_or(1, |road| road.length.max(1));
+        let arrival_time = time + travel_time;
+
+        // add event to character's events
+        let mut summary = format!(
+            "Character #{:?} moved to City #{:?}",
+            state.character, next_city
+        );
+        if let Some(haven) = state.heading_for.filter(|&haven| haven != next_city) {
+            summary += &format!(", heading for City #{:?}", haven);
+        }
+        let event_id = self.add_event(
+            vec![state.character],
+            time,
+            Some(arrival_time),
+            EventType::EventMove,
+            next_city,
+            summary,
+        )?;
+
+        // the character is on the road, and not in any city, until they arrive
+        state.journey = Some(Journey {
+            from: state.city,
+            to: next_city,
+            arrival_time,
+            event: event_id,
+        });
+        state.encountered = false;
+
+        Ok(())
+    }
+
+    // ends a character's journey in the city they were travelling to
+    fn arrive(
+        &mut self,
+        time: usize,
+        state: &mut CharacterState,
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+        calamities: &mut Calamities,
+    ) -> Result<(), WorldError> {
+        let journey = match state.journey.take() {
+            Some(journey) => journey,
+            None => return Ok(()),
+        };
+        let next_city = journey.to;
 
         // change character city to next city
         state.city = next_city;
         state.encountered = false;
 
+        // the character might bring the calamity with them
+        calamities.arrival(time, state.character, next_city);
+
         // recalculate probability map
-        let population = city_populations.get(&next_city).unwrap().len();
+        let population = city_populations
+            .get(&next_city)
+            .ok_or(WorldError::MissingCity(next_city))?
+            .len();
         // if the character moves to the last city, set probability of moving again to zero.
         // otherwise, the probability is proportional to half the population of the city plus the city's calamity state.
-        let next_city_id_num: usize = match next_city {
-            CityID(id) => id,
-        };
-        let last_city_id: usize = 0;
-        let next_city_calamity_state = city_calamity_states.get(&next_city).unwrap();
-        let new_move_prob = if next_city_id_num == last_city_id {
-            0
-        } else {
-            population / 4 + next_city_calamity_state * CALAMITY_DEADLINESS
-        };
-        let new_death_prob = next_city_calamity_state * CALAMITY_DEADLINESS;
+        let (new_move_prob, new_death_prob) =
+            calamities.city_weights(next_city, population, &state.personality, &self.roads)?;
+        let encounter_weight = self.encounter_weight(next_city, population, &state.personality)?;
         // update probabilities to the new city's context
-        println!(
+        trace!(
+            target: LOG_EVENTS,
             "event move: new encounter weight for char {:?}: {:?}",
             state.character,
-            population.pow(ENCOUNTER_POW)
+            encounter_weight
         );
         let weight_updates = [
             (0, &new_move_prob),
             (1, &new_death_prob),
-            (2, &(population.pow(ENCOUNTER_POW))),
+            (2, &encounter_weight),
         ];
         let update_result = state.event_probability_map.update_weights(&weight_updates);
         match update_result {
             Ok(_) => (),
             Err(_) => {
                 // if no actions are possible, character will stop forever
-                state.event_probability_map = WeightedIndex::new([0, 0, 0, 1]).unwrap();
+                state.event_probability_map = WeightedIndex::new([0, 0, 0, 1])?;
             }
         }
 
-        // add event to character's events
-        let event_id = self.add_event(
-            vec![state.character],
-            time,
-            None,
-            EventType::EventMove,
-            next_city,
-            format!(
-                "Character #{:?} moved to City #{:?}",
-                state.character, next_city
-            ),
-        );
-
-        // if the character had items, those items move with the character
+        // if the character had items, those items arrive with the character
         for item_index in 0..state.items.len() {
             let item_id = &mut state.items[item_index];
             self.items
                 .get_mut(item_id)
-                .unwrap()
+                .ok_or(WorldError::MissingItem(*item_id))?
                 .owner_records
                 .push(ItemMoveRecord {
                     time: time,
                     new_owner: Some(state.character),
                     new_location: Some(next_city),
-                    event: Some(event_id),
+                    event: Some(journey.event),
                 });
         }
+
+        Ok(())
     }
 
-    fn event_death(&mut self, time: usize, state: &mut CharacterState) {
+    fn event_death(
+        &mut self,
+        time: usize,
+        state: &mut CharacterState,
+        cause: DeathCause,
+    ) -> Result<(), WorldError> {
         // set all probabilities to zero except EventIdle
         state.dead = true;
-        state.event_probability_map = WeightedIndex::new(&[0, 0, 0, 1]).unwrap();
+        state.event_probability_map = WeightedIndex::new([0, 0, 0, 1])?;
+
+        // someone who dies on the road never gets where they were going. their journey ends there,
+        // and their death is recorded at the last city they reached
+        let summary = match state.journey.take() {
+            Some(journey) => {
+                self.events
+                    .get_mut(&journey.event)
+                    .ok_or(WorldError::MissingEvent(journey.event))?
+                    .end_time = Some(time);
+                format!(
+                    "Character #{:?} died {} on the road from City #{:?} to City #{:?}",
+                    state.character, cause, journey.from, journey.to
+                )
+            }
+            None => format!(
+                "Character #{:?} died {} in City #{:?}",
+                state.character, cause, state.city
+            ),
+        };
 
         // add death event
         let death_event = self.add_event(
             vec![state.character],
             time,
             None,
-            EventType::EventMove,
+            EventType::EventDeath(cause),
             state.city,
-            format!(
-                "Character #{:?} died in City #{:?}",
-                state.character, state.city
-            ),
-        );
+            summary,
+        )?;
 
         // if the character had items, those items get a record of that character's death
         for item_index in 0..state.items.len() {
             let item_id = &mut state.items[item_index];
             self.items
                 .get_mut(item_id)
-                .unwrap()
+                .ok_or(WorldError::MissingItem(*item_id))?
                 .owner_records
                 .push(ItemMoveRecord {
                     time: time,
@@ -434,35 +573,51 @@ impl World {
                     event: Some(death_event),
                 });
         }
+
+        Ok(())
     }
 
-    fn event_encounter(
+    fn event_encounter<R: Rng + ?Sized>(
         &mut self,
         time: usize,
         states: &mut Vec<CharacterState>,
         char_id: usize,
-        rng: &mut ThreadRng,
-        city_populations: &HashMap<CityID, Vec<CharacterID>>,
-    ) -> Result<EventID, ()> {
+        rng: &mut R,
+        city_populations: &BTreeMap<CityID, Vec<CharacterID>>,
+    ) -> Result<Option<EventID>, WorldError> {
         // pick random person from city to encounter
-        let state = states.get_mut(char_id).unwrap();
-        let city_population = city_populations.get(&state.city).unwrap();
-
-        let potential_encounter = city_population
+        let state = states
+            .get_mut(char_id)
+            .ok_or(WorldError::MissingCharacter(CharacterID(char_id)))?;
+        let city_population = city_populations
+            .get(&state.city)
+            .ok_or(WorldError::MissingCity(state.city))?;
+
+        // people seek out those they know, and keep away from their rivals
+        let others: Vec<CharacterID> = city_population
             .iter()
-            .filter(|&&id| id != state.character)
-            .choose(rng);
-
-        // if potential_encounter.is_none() {
-        //     return Err(())
-        // }
-        println!(
+            .copied()
+            .filter(|&id| id != state.character)
+            .collect();
+        let potential_encounter = others
+            .choose_weighted(rng, |&other| {
+                relationship::relationship(self, state.character, other)
+                    .map_or(1, Relationship::encounter_factor)
+            })
+            .ok();
+
+        trace!(
+            target: LOG_EVENTS,
             "city: {:?}, population: {:?}, character: {:?}",
             state.city,
             city_population.len(),
             state.character
         );
-        let &encountered = potential_encounter.unwrap();
+        // nobody else is in the city, so there's no one to encounter
+        let encountered = match potential_encounter {
+            Some(&encountered) => encountered,
+            None => return Ok(None),
+        };
 
         // add encounter event
         let encounter = self.add_event(
@@ -475,74 +630,679 @@ impl World {
                 "Character #{:?} encountered Character #{:?} in City #{:?}",
                 state.character, encountered, state.city
             ),
-        );
+        )?;
+        relationship::meet(self, state.character, encountered, time, rng)?;
+
+        // reduce probability of meeting after this to 0 for both of them
+        // (until they move to a new city)
+        state.stop_encountering()?;
+        states
+            .get_mut(encountered.0)
+            .ok_or(WorldError::MissingCharacter(encountered))?
+            .stop_encountering()?;
+
+        Ok(Some(encounter))
+    }
 
-        // reduce probability of meeting after this to 0
-        // (until character moves to a new city)
-        let new_weights = [(2, &0)];
-        println!(
-            "event encounter: new encounter weight for char {:?}: {:?}",
-            state.character, 0
+    // travellers on the same road might meet each other on the way
+    fn event_road_encounter<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        states: &mut [CharacterState],
+        state_index: usize,
+        rng: &mut R,
+    ) -> Result<Option<EventID>, WorldError> {
+        let state = &states[state_index];
+        let journey = match state.journey {
+            Some(journey) if !state.encountered => journey,
+            _ => return Ok(None),
+        };
+
+        let fellow_traveller = states
+            .iter()
+            .filter(|other| other.character != state.character)
+            .filter(|other| {
+                other
+                    .journey
+                    .is_some_and(|other_journey| other_journey.shares_road_with(&journey))
+            })
+            .map(|other| other.character)
+            .choose(rng);
+        let encountered = match fellow_traveller {
+            Some(encountered) => encountered,
+            None => return Ok(None), // nobody else on the road
+        };
+        if !rng.gen_bool(ROAD_ENCOUNTER_CHANCE) {
+            return Ok(None);
+        }
+
+        let character = state.character;
+        let encounter = self.add_event(
+            vec![character, encountered],
+            time,
+            None,
+            EventType::EventEncounter,
+            journey.to,
+            format!(
+                "Character #{:?} encountered Character #{:?} on the road to City #{:?}",
+                character, encountered, journey.to
+            ),
+        )?;
+        relationship::meet(self, character, encountered, time, rng)?;
+
+        states[state_index].stop_encountering()?;
+        states
+            .get_mut(encountered.0)
+            .ok_or(WorldError::MissingCharacter(encountered))?
+            .stop_encountering()?;
+
+        Ok(Some(encounter))
+    }
+
+    // after an encounter, the encountered character might pass one of their items on.
+    // the item is recorded as being wherever the character who received it is
+    fn pass_item<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        states: &mut [CharacterState],
+        state_index: usize,
+        encounter_id: EventID,
+        location: CityID,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        // retrieve encountered character, and check if they had any items, have a chance to pass on items. this requires mutably borrowing from states, so we lose access to state
+        let encountered_char_id = self
+            .events
+            .get(&encounter_id)
+            .ok_or(WorldError::MissingEvent(encounter_id))?
+            .characters[1];
+
+        let encountered_char_index = states
+            .iter()
+            .position(|x| x.character == encountered_char_id)
+            .ok_or(WorldError::MissingCharacter(encountered_char_id))?;
+
+        let encountered_char_state = &mut states[encountered_char_index];
+
+        let character_is_dead = encountered_char_state.dead;
+
+        if let Some(&item_id) = encountered_char_state.items.choose(rng) {
+            // how willing they are to part with it depends on who they are, and whether they made it
+            let made_it = self
+                .items
+                .get(&item_id)
+                .ok_or(WorldError::MissingItem(item_id))?
+                .owner_records
+                .first()
+                .is_some_and(|record| record.new_owner == Some(encountered_char_id));
+            // and on who they're giving it to
+            let receiver = states[state_index].character;
+            let giving_factor = relationship::relationship(self, encountered_char_id, receiver)
+                .map_or(1.0, Relationship::giving_factor);
+            let encountered_char_state = &mut states[encountered_char_index];
+            let giving_chance = encountered_char_state
+                .personality
+                .giving_chance(self.config.prob_item_passed * giving_factor, made_it);
+            if (rng.gen::<f32>() < giving_chance) || character_is_dead {
+                encountered_char_state.items.retain(|x| *x != item_id);
+
+                // reborrow state
+                let state = &mut states[state_index];
+                self.items
+                    .get_mut(&item_id)
+                    .ok_or(WorldError::MissingItem(item_id))?
+                    .owner_records
+                    .push(ItemMoveRecord {
+                        time,
+                        new_owner: Some(state.character),
+                        new_location: Some(location),
+                        event: Some(encounter_id),
+                    });
+
+                state.items.push(item_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    // the cities out of a list that haven't been abandoned
+    fn standing_cities(&self, cities: &[CityID]) -> Vec<CityID> {
+        cities
+            .iter()
+            .copied()
+            .filter(|city_id| self.cities.get(city_id).is_some_and(City::is_standing))
+            .collect()
+    }
+
+    // characters caught in a calamity can lose what they're carrying to it
+    fn event_item_losses<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        calamities: &Calamities,
+        states: &mut [CharacterState],
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        for calamity_state in &calamities.states {
+            let calamity = calamity_state.calamity();
+            if calamity.item_loss_chance() <= 0.0 {
+                continue;
+            }
+
+            for state in states.iter_mut() {
+                // travellers are out of the calamity's reach, and the dead have nothing left to lose
+                if state.dead
+                    || state.journey.is_some()
+                    || !calamity_state.reached.contains_key(&state.city)
+                {
+                    continue;
+                }
+
+                let (lost, kept): (Vec<ItemID>, Vec<ItemID>) = state
+                    .items
+                    .iter()
+                    .copied()
+                    .partition(|_| rng.gen_bool(calamity.item_loss_chance()));
+                state.items = kept;
+
+                for item_id in lost {
+                    let event_id = self.add_event(
+                        vec![state.character],
+                        time,
+                        None,
+                        EventType::EventLoss(item_id),
+                        state.city,
+                        format!(
+                            "Character #{:?} lost Item #{:?} to {} in City #{:?}",
+                            state.character,
+                            item_id,
+                            calamity.name(),
+                            state.city
+                        ),
+                    )?;
+                    // nobody owns the item any more, and it stays where it was lost
+                    self.items
+                        .get_mut(&item_id)
+                        .ok_or(WorldError::MissingItem(item_id))?
+                        .owner_records
+                        .push(ItemMoveRecord {
+                            time,
+                            new_owner: None,
+                            new_location: Some(state.city),
+                            event: Some(event_id),
+                        });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // records everything the calamities have done to cities since this was last called
+    fn event_calamity_milestones(
+        &mut self,
+        time: usize,
+        calamities: &mut Calamities,
+    ) -> Result<(), WorldError> {
+        for (kind, milestone) in calamities.take_milestones() {
+            let calamity_name = kind.calamity().name();
+            let (city_id, event_type, summary) = match milestone {
+                CalamityMilestone::Arrived(city_id) => (
+                    city_id,
+                    EventType::EventCalamityArrival(city_id, kind),
+                    format!("City #{:?} was reached by {}", city_id, calamity_name),
+                ),
+                CalamityMilestone::Fell(city_id) => (
+                    city_id,
+                    EventType::EventCityFall(city_id, kind),
+                    format!("City #{:?} fell to {}", city_id, calamity_name),
+                ),
+                CalamityMilestone::Receded(city_id) => (
+                    city_id,
+                    EventType::EventCalamityRecession(city_id, kind),
+                    format!("City #{:?} recovered from {}", city_id, calamity_name),
+                ),
+            };
+
+            // cities remember the first calamity to reach them, and the last to leave
+            let city = self
+                .cities
+                .get_mut(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?;
+            match milestone {
+                CalamityMilestone::Arrived(_) => {
+                    city.calamity_arrived = city.calamity_arrived.or(Some(time))
+                }
+                CalamityMilestone::Receded(_) => city.calamity_receded = Some(time),
+                CalamityMilestone::Fell(_) => (),
+            }
+            self.add_event(Vec::new(), time, None, event_type, city_id, summary)?;
+        }
+
+        Ok(())
+    }
+
+    // once the first calamity has started, another sometimes breaks out somewhere still standing
+    // that no calamity has got to
+    fn event_outbreak<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        calamities: &mut Calamities,
+        states: &[CharacterState],
+        rng: &mut R,
+    ) {
+        if time <= self.config.calamity_freq
+            || calamities.states.len() >= self.config.max_calamities
+            || !rng.gen_bool(self.config.outbreak_chance)
+        {
+            return;
+        }
+
+        let cities: Vec<CityID> = self
+            .cities
+            .iter()
+            .filter(|&(&city_id, city)| city.is_standing() && !calamities.is_in(city_id))
+            .map(|(&city_id, _)| city_id)
+            .collect();
+        let start_city = match cities.choose(rng) {
+            Some(&city_id) => city_id,
+            None => return, // the calamities are everywhere already
+        };
+        // whoever's there falls ill first, if it's the plague
+        let patient_zero = calamity::choose_patient_zero(start_city, states, rng);
+        let kind = match CalamityKind::ALL.choose(rng) {
+            Some(&kind) => kind,
+            None => return,
+        };
+        calamities.break_out(
+            kind,
+            start_city,
+            time,
+            patient_zero,
+            &self.roads,
+            &self.config,
         );
-        let update_result = state.event_probability_map.update_weights(&new_weights);
-        match update_result {
-            Ok(_) => (),
-            Err(_) => {
-                // if no actions are possible, character will stop forever
-                state.event_probability_map = WeightedIndex::new([0, 0, 0, 1]).unwrap();
+    }
+
+    // cities the calamity has lingered in for too long are abandoned, and abandoned cities fall into ruin
+    fn event_abandonments(
+        &mut self,
+        time: usize,
+        calamities: &Calamities,
+    ) -> Result<(), WorldError> {
+        let city_ids: Vec<CityID> = self.cities.keys().copied().collect();
+        for city_id in city_ids {
+            let calamity_time = calamities.calamity_time(city_id);
+            let city = self
+                .cities
+                .get_mut(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?;
+
+            match (city.abandoned, city.ruined) {
+                (None, _) if calamity_time >= self.config.abandon_after => {
+                    city.abandoned = Some(time);
+                    self.add_event(
+                        Vec::new(),
+                        time,
+                        None,
+                        EventType::EventAbandonment(city_id),
+                        city_id,
+                        format!("City #{:?} was abandoned", city_id),
+                    )?;
+                }
+                (Some(abandoned), None) if time >= abandoned + self.config.ruin_after => {
+                    city.ruined = Some(time);
+                    self.add_event(
+                        Vec::new(),
+                        time,
+                        None,
+                        EventType::EventRuin(city_id),
+                        city_id,
+                        format!("City #{:?} fell into ruin", city_id),
+                    )?;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    // cities the calamity hasn't reached yet are sometimes renamed
+    fn event_renamings<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        calamities: &Calamities,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        let city_ids: Vec<CityID> = self.cities.keys().copied().collect();
+        for city_id in city_ids {
+            let calamity_time = calamities.calamity_time(city_id);
+            let city = self
+                .cities
+                .get_mut(&city_id)
+                .ok_or(WorldError::MissingCity(city_id))?;
+            if calamity_time > 0 || !city.is_standing() || !rng.gen_bool(self.config.rename_chance)
+            {
+                continue;
+            }
+
+            let new_name = City::name_gen(rng);
+            let old_name = std::mem::replace(&mut city.name, new_name.clone());
+            city.former_names.push(FormerName {
+                name: old_name.clone(),
+                until: time,
+            });
+            self.add_event(
+                Vec::new(),
+                time,
+                None,
+                EventType::EventRenaming(city_id),
+                city_id,
+                format!(
+                    "City #{:?} was renamed from {} to {}",
+                    city_id, old_name, new_name
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // sometimes a new city is founded somewhere the calamity hasn't reached yet,
+    // with roads to a city in the layer before it and a city in the layer after it
+    fn event_founding<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        calamities: &mut Calamities,
+        rng: &mut R,
+    ) -> Result<Option<CityID>, WorldError> {
+        if !rng.gen_bool(self.config.found_chance) {
+            return Ok(None);
+        }
+
+        // the start layer only ever has the start city, and a new city needs somewhere onwards to lead
+        let first_layer = calamities
+            .furthest_layer(&self.roads)
+            .map_or(1, |layer| layer + 1);
+        let last_layer = self.roads.num_layers().saturating_sub(1);
+        let layer = match (first_layer.max(1)..last_layer).choose(rng) {
+            Some(layer) => layer,
+            None => return Ok(None), // the calamity is everywhere already
+        };
+        let previous_cities = self.roads.layer(layer - 1).to_vec();
+        let next_cities = self.roads.layer(layer + 1).to_vec();
+        let previous_city = match self.standing_cities(&previous_cities).choose(rng) {
+            Some(&city_id) => city_id,
+            None => return Ok(None), // nobody left to go and found it
+        };
+        let next_city = match self.standing_cities(&next_cities).choose(rng) {
+            Some(&city_id) => city_id,
+            None => match next_cities.choose(rng) {
+                Some(&city_id) => city_id,
+                None => return Ok(None),
+            },
+        };
+
+        let position = position_in_layer(self, layer);
+        let new_city = self.add_city(layer, rng);
+        let city = self
+            .cities
+            .get_mut(&new_city)
+            .ok_or(WorldError::MissingCity(new_city))?;
+        city.position = position;
+        city.traits = CityTraits::new(rng);
+        city.founded = time;
+        calamities.add_city(new_city);
+
+        for other_city in [previous_city, next_city] {
+            let other_position = self
+                .cities
+                .get(&other_city)
+                .ok_or(WorldError::MissingCity(other_city))?
+                .position;
+            let length = travel_time(&position, &other_position).max(1);
+            self.add_road(other_city, new_city, RoadDirection::TwoWay, length)?;
+        }
+
+        self.add_event(
+            Vec::new(),
+            time,
+            None,
+            EventType::EventFounding(new_city),
+            new_city,
+            format!("City #{:?} was founded", new_city),
+        )?;
+
+        Ok(Some(new_city))
+    }
+
+    // characters die of old age once they've lived out their lifespan, and before then
+    // sometimes fall ill (more so the older they get) or have an accident on the road
+    fn event_natural_deaths<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        states: &mut [CharacterState],
+        rng: &mut R,
+    ) -> Result<bool, WorldError> {
+        let mut died = false;
+        for state in states.iter_mut() {
+            if state.dead {
+                continue;
             }
+            let character = self
+                .characters
+                .get(&state.character)
+                .ok_or(WorldError::MissingCharacter(state.character))?;
+            let age = character.age_at(time);
+            let lifespan = character.lifespan.max(1);
+            let illness_chance = self.config.illness_chance * age as f64 / lifespan as f64;
+            let accident_chance = state
+                .personality
+                .accident_chance(self.config.accident_chance);
+
+            let cause = if age >= lifespan {
+                DeathCause::OldAge
+            } else if rng.gen_bool(illness_chance.min(1.0)) {
+                DeathCause::Illness
+            } else if state.journey.is_some() && rng.gen_bool(accident_chance) {
+                DeathCause::Accident
+            } else {
+                continue;
+            };
+            self.event_death(time, state, cause)?;
+            died = true;
         }
 
-        state.encountered = true;
-        match encountered {
-            CharacterID(id) => states.get_mut(id).unwrap().encountered = true,
+        Ok(died)
+    }
+
+    // rivals who run into each other sometimes come to blows, and one of them doesn't get up again
+    fn event_violence<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        states: &mut [CharacterState],
+        encounter_id: EventID,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        let characters = self
+            .events
+            .get(&encounter_id)
+            .ok_or(WorldError::MissingEvent(encounter_id))?
+            .characters
+            .clone();
+        let (first, second) = match characters[..] {
+            [first, second] => (first, second),
+            _ => return Err(WorldError::IncompleteRecord("encountered character")),
+        };
+        let is_dead = |id: CharacterID| {
+            states
+                .get(id.0)
+                .map(|state| state.dead)
+                .ok_or(WorldError::MissingCharacter(id))
+        };
+        if is_dead(first)?
+            || is_dead(second)?
+            || relationship::relationship(self, first, second).map(Relationship::kind)
+                != Some(RelationshipKind::Rival)
+            || !rng.gen_bool(self.config.violence_chance)
+        {
+            return Ok(());
         }
 
-        Ok(encounter)
+        let victim = if rng.gen_bool(0.5) { first } else { second };
+        let state = states
+            .get_mut(victim.0)
+            .ok_or(WorldError::MissingCharacter(victim))?;
+        self.event_death(time, state, DeathCause::Violence)
+    }
 
-        // to do - reduce probability of meeting to 0 for the encountered character as well
+    // grown characters settled somewhere the calamity isn't sometimes have a child, with their lover
+    // if their lover is there with them. children start out where they were born, as their parents' kin.
+    // returns whether anyone was born
+    fn event_births<R: Rng + ?Sized>(
+        &mut self,
+        time: usize,
+        states: &mut Vec<CharacterState>,
+        calamities: &Calamities,
+        rng: &mut R,
+    ) -> Result<bool, WorldError> {
+        // only those already alive at the start of the time step, so nobody is born and has children at once
+        let num_states = states.len();
+        let mut born = false;
+        for state_index in 0..num_states {
+            let state = &states[state_index];
+            if state.dead || state.journey.is_some() || calamities.is_in(state.city) {
+                continue;
+            }
+            let parent = state.character;
+            let city_id = state.city;
+            let parent_age = self
+                .characters
+                .get(&parent)
+                .ok_or(WorldError::MissingCharacter(parent))?
+                .age_at(time);
+            if parent_age < self.config.come_of_age || !rng.gen_bool(self.config.birth_chance) {
+                continue;
+            }
+
+            let mut parents = vec![parent];
+            let lovers: Vec<CharacterID> = states
+                .iter()
+                .filter(|other| {
+                    !other.dead
+                        && other.journey.is_none()
+                        && other.city == city_id
+                        && relationship::relationship(self, parent, other.character)
+                            .and_then(|relationship| relationship.kind_at(time))
+                            == Some(RelationshipKind::Lover)
+                })
+                .map(|other| other.character)
+                .collect();
+            if let Some(&lover) = lovers.choose(rng) {
+                parents.push(lover);
+            }
+
+            let names: Vec<String> = parents
+                .iter()
+                .map(|parent| {
+                    self.characters
+                        .get(parent)
+                        .map(|character| character.name.clone())
+                        .ok_or(WorldError::MissingCharacter(*parent))
+                })
+                .collect::<Result<_, _>>()?;
+            let child = self.add_character(rng);
+            let character = self
+                .characters
+                .get_mut(&child)
+                .ok_or(WorldError::MissingCharacter(child))?;
+            character.name =
+                Character::inherited_name(&names[0], names.get(1).map(String::as_str), rng);
+            character.born = time as isize;
+            character.lifespan = Character::lifespan_gen(self.config.lifespan, rng);
+            let personality = character.personality;
+            for &parent in &parents {
+                relationship::relate(self, child, parent, time, RelationshipKind::Family)?;
+            }
+
+            let mut characters = vec![child];
+            characters.extend(&parents);
+            let parent_summary: Vec<String> = parents
+                .iter()
+                .map(|parent| format!("Character #{:?}", parent))
+                .collect();
+            self.add_event(
+                characters,
+                time,
+                None,
+                EventType::EventBirth,
+                city_id,
+                format!(
+                    "Character #{:?} was born to {} in City #{:?}",
+                    child,
+                    parent_summary.join(" and "),
+                    city_id
+                ),
+            )?;
+            states.push(CharacterState::new(
+                child,
+                city_id,
+                personality,
+                self.characters.len(),
+            )?);
+            born = true;
+        }
+
+        Ok(born)
     }
 
     // generates events chronologically and places them in the event lists of cities and characters.
     // a character only visits a city once and only encounters at most one other character in a city
     // run generate_world before running this or perish in the doomed worldless narrative that you've created
-    pub fn generate_events(&mut self) {
-        let mut rng = thread_rng();
+    pub fn generate_events(&mut self) -> Result<(), WorldError> {
+        let mut rng = seeded_rng(self.seed, EVENTGEN_STREAM);
+        self.generate_events_with_rng(&mut rng)
+    }
 
-        println!("---- Event Generation ----");
+    pub fn generate_events_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<(), WorldError> {
+        let num_characters = self.characters.len();
+        let start_city = self.roads.start_city().ok_or(WorldError::NoStartCity)?;
 
         // set up initial states for each character
         let mut states = Vec::new(); // in order of character id
-        for char_id in 0..NUM_CHARACTERS {
-            let starting_weights: [usize; NUM_EVENTS] = [
-                cmp::max(NUM_CHARACTERS / 2, 1), // EventMove
-                0,                               // EventDeath
-                NUM_CHARACTERS, // EventEncounter (keep this proportional to the number of other characters in the same city)
-                1,              // EventIdle
-            ];
-            let event_probability_map = WeightedIndex::new(&starting_weights).unwrap();
-            states.push(CharacterState {
-                character: CharacterID(char_id),
-                city: self.layers[0][0], // start city
-                items: Vec::new(),       // starting inventory is empty
-                event_probability_map,
-                dead: false,
-                encountered: false,
-            });
+        for char_id in 0..num_characters {
+            let personality = self
+                .characters
+                .get(&CharacterID(char_id))
+                .ok_or(WorldError::MissingCharacter(CharacterID(char_id)))?
+                .personality;
+            states.push(CharacterState::new(
+                CharacterID(char_id),
+                start_city,
+                personality,
+                num_characters,
+            )?);
         }
 
-        println!("Generating items...");
+        info!(target: LOG_EVENTS, "generating items...");
         // add items
-        for _ in 0..NUM_ITEMS {
-            let creator_id = self
-                .characters
-                .keys()
-                .choose(&mut rand::thread_rng())
-                .unwrap()
-                .clone();
-            let start_city = self.layers[0][0];
-
-            let item = self.add_item(ItemType::new(&mut rng), 0, creator_id, start_city);
+        for _ in 0..self.config.num_items {
+            let creator_id = match self.characters.keys().choose(rng) {
+                Some(&creator_id) => creator_id,
+                None => break, // nobody to create items
+            };
+
+            let start_city_traits = self
+                .cities
+                .get(&start_city)
+                .ok_or(WorldError::MissingCity(start_city))?
+                .traits;
+            let item_type = ItemType::made_in(&start_city_traits, rng);
+            let item = self.add_item(item_type, 0, creator_id, start_city)?;
 
             for state_index in 0..states.len() {
                 let state = &mut states[state_index];
@@ -556,7 +1316,8 @@ impl World {
         fn get_characters_in_city(city: CityID, states: &Vec<CharacterState>) -> Vec<CharacterID> {
             let mut chars_in_city = Vec::new();
             for state in states.iter() {
-                if state.city == city {
+                // travellers aren't in any city
+                if state.city == city && state.journey.is_none() {
                     chars_in_city.push(state.character);
                 }
             }
@@ -565,7 +1326,7 @@ impl World {
 
         fn recalculate_city_populations(
             cities: Vec<&CityID>,
-            city_populations: &mut HashMap<CityID, Vec<CharacterID>>,
+            city_populations: &mut BTreeMap<CityID, Vec<CharacterID>>,
             states: &Vec<CharacterState>,
         ) {
             // reset and recalculate
@@ -577,34 +1338,94 @@ impl World {
 
         // set up values for history
         let mut time = 0;
-        let mut calamity_state = CalamityState::new(self.cities.keys().collect());
+        let mut calamities = Calamities::new(
+            self.cities.keys().collect(),
+            &self.roads,
+            &self.config,
+            &states,
+            rng,
+        )?;
         // set initial city populations
-        let mut city_populations: HashMap<CityID, Vec<CharacterID>> = HashMap::new();
+        let mut city_populations: BTreeMap<CityID, Vec<CharacterID>> = BTreeMap::new();
         recalculate_city_populations(self.cities.keys().collect(), &mut city_populations, &states);
 
-        println!("Generating events...");
+        info!(target: LOG_EVENTS, "generating events...");
         // start running history
-        while time <= MAX_TIME {
-            println!("time: {:?}", time);
+        while time <= self.config.max_time {
+            debug!(target: LOG_EVENTS, "time: {:?}", time);
             // step calamity movement
-            calamity_state.calamity_step(
-                time,
-                &self.layers.to_vec(),
-                &mut states,
-                &city_populations,
-            );
+            self.event_outbreak(time, &mut calamities, &states, rng);
+            calamities.calamity_step(time, &self.roads, &mut states, &city_populations, rng)?;
+            // note who is still holding out in the cities the calamity has just reached
+            calamities.check_holdouts(&states, &city_populations);
+
+            // the calamity might take things from the people caught in it
+            self.event_item_losses(time, &calamities, &mut states, rng)?;
+
+            // cities change along with the calamity
+            self.event_calamity_milestones(time, &mut calamities)?;
+            self.event_abandonments(time, &calamities)?;
+            self.event_renamings(time, &calamities, rng)?;
+            let founded = self.event_founding(time, &mut calamities, rng)?.is_some();
+            // people die and are born whether or not there's a calamity
+            let died = self.event_natural_deaths(time, &mut states, rng)?;
+            let born = self.event_births(time, &mut states, &calamities, rng)?;
+            if founded || died || born {
+                recalculate_city_populations(
+                    self.cities.keys().collect(),
+                    &mut city_populations,
+                    &states,
+                );
+            }
 
             // update each character's state
             for state_index in 0..states.len() {
+                // characters on the road can only meet other travellers until they arrive
+                if let Some(journey) = states[state_index].journey {
+                    if time < journey.arrival_time {
+                        let encounter_id =
+                            self.event_road_encounter(time, &mut states, state_index, rng)?;
+                        if let Some(encounter_id) = encounter_id {
+                            self.pass_item(
+                                time,
+                                &mut states,
+                                state_index,
+                                encounter_id,
+                                journey.to,
+                                rng,
+                            )?;
+                            calamities.contact(&self.events[&encounter_id].characters, &states);
+                            self.event_violence(time, &mut states, encounter_id, rng)?;
+                        }
+                        continue;
+                    }
+
+                    self.arrive(
+                        time,
+                        &mut states[state_index],
+                        &city_populations,
+                        &mut calamities,
+                    )?;
+                    recalculate_city_populations(
+                        self.cities.keys().collect(),
+                        &mut city_populations,
+                        &states,
+                    );
+                }
+
                 // determine next events for each character
                 let state = &mut states[state_index];
 
                 // update encounter probability
-                let population = city_populations.get(&state.city).unwrap().len() - 1;
+                let population = city_populations
+                    .get(&state.city)
+                    .ok_or(WorldError::MissingCity(state.city))?
+                    .len()
+                    - 1;
                 let encounter_weight = if state.encountered {
                     0
                 } else {
-                    population.pow(ENCOUNTER_POW)
+                    self.encounter_weight(state.city, population, &state.personality)?
                 };
                 let new_weights = [(2, &encounter_weight)];
                 let update_result = state.event_probability_map.update_weights(&new_weights);
@@ -612,78 +1433,42 @@ impl World {
                     Ok(_) => (),
                     Err(_) => {
                         // if no actions are possible, character will stop forever
-                        state.event_probability_map = WeightedIndex::new([0, 0, 0, 1]).unwrap();
+                        state.event_probability_map = WeightedIndex::new([0, 0, 0, 1])?;
                     }
                 }
 
                 // choose next event
-                let next_event = &LIST_EVENTS[state.event_probability_map.sample(&mut rng)];
+                let next_event = &LIST_EVENTS[state.event_probability_map.sample(rng)];
 
                 // carry out event
                 match next_event {
                     EventType::EventIdle => (), // do nothing (event idle is not logged)
                     EventType::EventMove => {
-                        self.event_move(
-                            time,
-                            state,
-                            &mut rng,
-                            &city_populations,
-                            &calamity_state.city_states,
-                        );
+                        self.event_move(time, state, &calamities, rng)?;
                     }
-                    EventType::EventDeath => {
-                        self.event_death(time, state);
+                    EventType::EventDeath(cause) => {
+                        self.event_death(time, state, *cause)?;
                     }
                     EventType::EventEncounter => {
                         // add the encounter event
                         let encounter_id = match state.character {
-                            CharacterID(id) => self.event_encounter(
-                                time,
-                                &mut states,
-                                id,
-                                &mut rng,
-                                &city_populations,
-                            ),
+                            CharacterID(id) => {
+                                self.event_encounter(time, &mut states, id, rng, &city_populations)?
+                            }
                         };
 
-                        if encounter_id.is_ok() {
-                            let encounter_id = encounter_id.unwrap();
-                            // retrieve encountered character, and check if they had any items, have a chance to pass on items. this requires mutably borrowing from states, so we lose access to state
-                            let encountered_char_id =
-                                self.events.get(&encounter_id).unwrap().characters[1].clone();
-
-                            let encountered_char_index = states
-                                .iter()
-                                .position(|x| x.character == encountered_char_id)
-                                .unwrap();
-
-                            let encountered_char_state = &mut states[encountered_char_index];
-
-                            let character_is_dead = encountered_char_state.dead;
-
-                            if (encountered_char_state.items.len() > 0)
-                                && ((rng.gen::<f32>() < PROB_ITEM_PASSED) || character_is_dead)
-                            {
-                                let item_id = encountered_char_state
-                                    .items
-                                    .choose(&mut rng)
-                                    .unwrap()
-                                    .clone();
-                                encountered_char_state.items.retain(|x| *x != item_id);
-
-                                // reborrow state
-                                let state = &mut states[state_index];
-                                self.items.get_mut(&item_id).unwrap().owner_records.push(
-                                    ItemMoveRecord {
-                                        time: time,
-                                        new_owner: Some(state.character),
-                                        new_location: Some(state.city),
-                                        event: Some(encounter_id),
-                                    },
-                                );
-
-                                state.items.push(item_id);
-                            }
+                        if let Some(encounter_id) = encounter_id {
+                            let location = states[state_index].city;
+                            self.pass_item(
+                                time,
+                                &mut states,
+                                state_index,
+                                encounter_id,
+                                location,
+                                rng,
+                            )?;
+                            calamities.contact(&self.events[&encounter_id].characters, &states);
+                            self.event_violence(time, &mut states, encounter_id, rng)?;
                         }
                     }
                     _ => (),
@@ -695,20 +1480,208 @@ impl World {
                     &states,
                 );
             }
+
+            // record any cities that fell or caught the calamity from travellers while everyone acted
+            calamities.check_holdouts(&states, &city_populations);
+            self.event_calamity_milestones(time, &mut calamities)?;
             time += 1;
         }
-        println!("Generated {:?} events", self.event_id_counter);
+        info!(target: LOG_EVENTS, "generated {:?} events", self.event_id_counter);
+
+        Ok(())
     }
 }
 
 // -- City class --
 
+#[derive(Serialize, Deserialize)]
 pub struct City {
     pub name: String,
-    pub neighbours: Vec<CityID>,
+    pub position: Position, // where the city is on the map
+    #[serde(default)]
+    pub traits: CityTraits,
+    #[serde(default)]
+    pub founded: usize, // cities on the original map were founded at time 0
+    #[serde(default)]
+    pub calamity_arrived: Option<usize>, // when the calamity first reached the city, if it did
+    #[serde(default)]
+    pub calamity_receded: Option<usize>, // when the calamity left the city, if it did
+    #[serde(default)]
+    pub abandoned: Option<usize>, // when the calamity drove everyone out, if it did
+    #[serde(default)]
+    pub ruined: Option<usize>, // when the city fell into ruin after being abandoned
+    #[serde(default)]
+    pub former_names: Vec<FormerName>, // oldest first
     pub events: Vec<EventID>,
 }
 
+// a name a city went by before it was renamed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FormerName {
+    pub name: String,
+    pub until: usize, // when the city was renamed
+}
+
+// a point on the map. layers run along x, and the cities in a layer are spread out along y
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Position {
+    pub fn distance_to(&self, other: &Position) -> f32 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+// how many people live in a city
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CitySize {
+    Hamlet,
+    #[default]
+    Village,
+    Town,
+    City,
+}
+
+// what the people of a city are like
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Culture {
+    #[default]
+    Mercantile,
+    Devout,
+    Scholarly,
+    Nomadic,
+}
+
+// what a city is known for making
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Craft {
+    #[default]
+    Pottery,
+    Textiles,
+    Metalwork,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Biome {
+    Forest,
+    #[default]
+    Plains,
+    Hills,
+    Coast,
+    Marsh,
+}
+
+const CITY_SIZES: [(CitySize, usize); 4] = [
+    (CitySize::Hamlet, 3),
+    (CitySize::Village, 4),
+    (CitySize::Town, 2),
+    (CitySize::City, 1),
+];
+const CULTURES: [Culture; 4] = [
+    Culture::Mercantile,
+    Culture::Devout,
+    Culture::Scholarly,
+    Culture::Nomadic,
+];
+const CRAFTS: [Craft; 3] = [Craft::Pottery, Craft::Textiles, Craft::Metalwork];
+const BIOMES: [Biome; 5] = [
+    Biome::Forest,
+    Biome::Plains,
+    Biome::Hills,
+    Biome::Coast,
+    Biome::Marsh,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CityTraits {
+    pub size: CitySize,
+    pub culture: Culture,
+    pub craft: Craft,
+    pub biome: Biome,
+}
+
+impl CityTraits {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        CityTraits {
+            size: CITY_SIZES
+                .choose_weighted(rng, |&(_, weight)| weight)
+                .map_or(CitySize::default(), |&(size, _)| size),
+            culture: *CULTURES.choose(rng).unwrap(),
+            craft: *CRAFTS.choose(rng).unwrap(),
+            biome: *BIOMES.choose(rng).unwrap(),
+        }
+    }
+
+    // encounter weights are multiplied by this. bigger cities and market towns bring more people together
+    pub fn encounter_factor(&self) -> usize {
+        let size_factor = match self.size {
+            CitySize::Hamlet | CitySize::Village => 1,
+            CitySize::Town => 2,
+            CitySize::City => 3,
+        };
+        let culture_factor = match self.culture {
+            Culture::Mercantile => 1,
+            _ => 0,
+        };
+        size_factor + culture_factor
+    }
+
+    // e.g. "a town known for its pottery, out in the hills"
+    pub fn describe(&self) -> String {
+        format!(
+            "a {} known for its {}, out in the {}",
+            self.size, self.craft, self.biome
+        )
+    }
+}
+
+impl std::fmt::Display for CitySize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CitySize::Hamlet => write!(f, "hamlet"),
+            CitySize::Village => write!(f, "village"),
+            CitySize::Town => write!(f, "town"),
+            CitySize::City => write!(f, "city"),
+        }
+    }
+}
+
+impl std::fmt::Display for Culture {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Culture::Mercantile => write!(f, "mercantile"),
+            Culture::Devout => write!(f, "devout"),
+            Culture::Scholarly => write!(f, "scholarly"),
+            Culture::Nomadic => write!(f, "nomadic"),
+        }
+    }
+}
+
+impl std::fmt::Display for Craft {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Craft::Pottery => write!(f, "pottery"),
+            Craft::Textiles => write!(f, "textiles"),
+            Craft::Metalwork => write!(f, "metalwork"),
+        }
+    }
+}
+
+impl std::fmt::Display for Biome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Biome::Forest => write!(f, "forest"),
+            Biome::Plains => write!(f, "plains"),
+            Biome::Hills => write!(f, "hills"),
+            Biome::Coast => write!(f, "coast"),
+            Biome::Marsh => write!(f, "marshes"),
+        }
+    }
+}
+
 const SOFTLETTERS: &'static [&'static str] = &["sh", "l", "m", "n", "r"];
 const HARDLETTERS: &'static [&'static str] = &["p", "b", "t", "g"];
 const VOWELS: &'static [&'static str] = &["a", "e", "i", "o", "oo", "ai"];
@@ -718,18 +1691,41 @@ impl City {
     pub fn new(name: String) -> Self {
         City {
             name,
-            neighbours: Vec::new(),
+            position: Position::default(),
+            traits: CityTraits::default(),
+            founded: 0,
+            calamity_arrived: None,
+            calamity_receded: None,
+            abandoned: None,
+            ruined: None,
+            former_names: Vec::new(),
             events: Vec::new(),
         }
     }
-    pub fn name_gen() -> String {
+
+    pub fn is_standing(&self) -> bool {
+        self.abandoned.is_none()
+    }
+
+    // whether the city had been founded and not yet abandoned at a given time
+    pub fn was_standing_at(&self, time: usize) -> bool {
+        time >= self.founded && self.abandoned.is_none_or(|abandoned| time < abandoned)
+    }
+
+    // the name the city went by at a given time
+    pub fn name_at(&self, time: usize) -> &str {
+        self.former_names
+            .iter()
+            .find(|former_name| time < former_name.until)
+            .map_or(&self.name, |former_name| &former_name.name)
+    }
+    pub fn name_gen<R: Rng + ?Sized>(rng: &mut R) -> String {
         let mut first_syllable = "".to_string();
-        let mut rng = rand::thread_rng();
 
-        first_syllable.push_str(HARDLETTERS.choose(&mut rng).expect(""));
-        first_syllable.push_str(VOWELS.choose(&mut rng).expect(""));
-        first_syllable.push_str(SOFTLETTERS.choose(&mut rng).expect(""));
-        first_syllable.push_str(SUFFIXES.choose(&mut rng).expect(""));
+        first_syllable.push_str(HARDLETTERS.choose(rng).expect(""));
+        first_syllable.push_str(VOWELS.choose(rng).expect(""));
+        first_syllable.push_str(SOFTLETTERS.choose(rng).expect(""));
+        first_syllable.push_str(SUFFIXES.choose(rng).expect(""));
 
         first_syllable
     }
@@ -741,15 +1737,23 @@ const NAME_HARDLETTERS: &'static [&'static str] = &["p", "b", "t", "ch", "t", "k
 const NAME_VOWELS: &'static [&'static str] = &["a", "e", "ae", "io", "ai", "u"];
 const NAME_SOFTLETTERS: &'static [&'static str] = &["th", "nn", "ni", "sh"];
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Character {
     // used in textgen
     pub name: String,
     pub pronouns: Pronouns,
+    #[serde(default)]
+    pub born: isize, // the year they were born, before the history starts for everyone there from the start
+    #[serde(default)]
+    pub lifespan: usize, // how old they'll live to if nothing else gets them first
+    #[serde(default)]
+    pub personality: Personality,
+    #[serde(default)]
+    pub relationships: BTreeMap<CharacterID, Relationship>, // everyone they know, and how
     pub events: Vec<EventID>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Pronouns {
     // probably gonna be needed for text gen
     pub nominative: String, // she, him, they etc.
@@ -765,63 +1769,286 @@ const PRONOUNS: &'static [&'static [&'static str]] = &[
 ];
 
 impl Character {
-    fn name_gen() -> String {
+    fn name_gen<R: Rng + ?Sized>(rng: &mut R) -> String {
+        let mut name = Self::name_opening_gen(rng);
+        name.push_str(&Self::name_ending_gen(rng));
+        name
+    }
+
+    fn name_opening_gen<R: Rng + ?Sized>(rng: &mut R) -> String {
         let mut first_syllable = "".to_string();
-        let mut rng = rand::thread_rng();
-        first_syllable.push_str(NAME_HARDLETTERS.choose(&mut rng).expect(""));
-        first_syllable.push_str(NAME_VOWELS.choose(&mut rng).expect(""));
-        first_syllable.push_str(NAME_SOFTLETTERS.choose(&mut rng).expect(""));
-        first_syllable.push_str(NAME_VOWELS.choose(&mut rng).expect(""));
+        first_syllable.push_str(NAME_HARDLETTERS.choose(rng).expect(""));
+        first_syllable.push_str(NAME_VOWELS.choose(rng).expect(""));
 
         first_syllable
     }
 
-    fn pronoun_gen() -> Pronouns {
-        let mut rng = rand::thread_rng();
+    fn name_ending_gen<R: Rng + ?Sized>(rng: &mut R) -> String {
+        let mut ending = "".to_string();
+        ending.push_str(NAME_SOFTLETTERS.choose(rng).expect(""));
+        ending.push_str(NAME_VOWELS.choose(rng).expect(""));
+
+        ending
+    }
+
+    // the hard letter and vowel a generated name starts with, e.g. "tae" in "taenni"
+    fn name_opening(name: &str) -> Option<&str> {
+        let rest = NAME_HARDLETTERS
+            .iter()
+            .find_map(|hard| name.strip_prefix(hard))?;
+        let vowel = NAME_VOWELS
+            .iter()
+            .filter(|vowel| rest.starts_with(*vowel))
+            .max_by_key(|vowel| vowel.len())?;
+        Some(&name[..name.len() - rest.len() + vowel.len()])
+    }
+
+    // a child's name starts like their first parent's and ends like their other parent's,
+    // or with something new if there's only the one
+    fn inherited_name<R: Rng + ?Sized>(
+        parent: &str,
+        other_parent: Option<&str>,
+        rng: &mut R,
+    ) -> String {
+        let mut name = match Self::name_opening(parent) {
+            Some(opening) => opening.to_string(),
+            None => Self::name_opening_gen(rng),
+        };
+        let ending = other_parent.and_then(|other_parent| {
+            Self::name_opening(other_parent).map(|opening| &other_parent[opening.len()..])
+        });
+        match ending {
+            Some(ending) if !ending.is_empty() => name.push_str(ending),
+            _ => name.push_str(&Self::name_ending_gen(rng)),
+        }
+        name
+    }
+
+    fn pronoun_gen<R: Rng + ?Sized>(rng: &mut R) -> Pronouns {
         Pronouns {
-            nominative: PRONOUNS[0].choose(&mut rng).unwrap().to_string(),
-            accusative: PRONOUNS[1].choose(&mut rng).unwrap().to_string(),
-            dep_genitive: PRONOUNS[2].choose(&mut rng).unwrap().to_string(),
+            nominative: PRONOUNS[0].choose(rng).unwrap().to_string(),
+            accusative: PRONOUNS[1].choose(rng).unwrap().to_string(),
+            dep_genitive: PRONOUNS[2].choose(rng).unwrap().to_string(),
         }
     }
 
     pub fn has_died(&self, world: &World) -> bool {
-        let death_events: Vec<&Event> = self
-            .events
+        self.death_cause(world).is_some()
+    }
+
+    // what the character died of, if they've died
+    pub fn death_cause(&self, world: &World) -> Option<DeathCause> {
+        self.events
             .iter()
-            .map(|event_id| world.events.get(event_id).unwrap())
-            .filter(|&event| event.event_type == EventType::EventDeath)
-            .collect();
+            .filter_map(|event_id| world.events.get(event_id))
+            .find_map(|event| match event.event_type {
+                EventType::EventDeath(cause) => Some(cause),
+                _ => None,
+            })
+    }
+
+    // how old the character was at the given time
+    pub fn age_at(&self, time: usize) -> usize {
+        (time as isize - self.born).max(0) as usize
+    }
 
-        !death_events.is_empty()
+    // lifespans vary by up to a quarter either way
+    fn lifespan_gen<R: Rng + ?Sized>(lifespan: usize, rng: &mut R) -> usize {
+        let spread = lifespan / 4;
+        rng.gen_range(lifespan - spread..=lifespan + spread).max(1)
     }
 
-    pub fn new() -> Self {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Character {
-            name: Self::name_gen(),
-            pronouns: Self::pronoun_gen(),
+            name: Self::name_gen(rng),
+            pronouns: Self::pronoun_gen(rng),
+            born: 0,
+            lifespan: 0,
+            personality: Personality::new(rng),
+            relationships: BTreeMap::new(),
             events: Vec::new(),
         }
     }
 }
 
+// how strongly a character shows a personality trait
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraitLevel {
+    Low,
+    #[default]
+    Average,
+    High,
+}
+
+// how a character tends to behave
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Personality {
+    pub wanderlust: TraitLevel,     // how readily they move on
+    pub sociability: TraitLevel,    // how readily they seek other people out
+    pub greed: TraitLevel,          // how tightly they hold on to what they're carrying
+    pub caution: TraitLevel, // how readily they sit tight, and how quickly they flee the calamity
+    pub sentimentality: TraitLevel, // how tightly they hold on to things they made themselves
+}
+
+// a personality trait on its own, for picking lines about it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PersonalityTrait {
+    Wanderlust,
+    Sociability,
+    Greed,
+    Caution,
+    Sentimentality,
+}
+
+// most people are average in any given way
+const TRAIT_LEVELS: [(TraitLevel, usize); 3] = [
+    (TraitLevel::Low, 1),
+    (TraitLevel::Average, 2),
+    (TraitLevel::High, 1),
+];
+
+impl TraitLevel {
+    fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        TRAIT_LEVELS
+            .choose_weighted(rng, |&(_, weight)| weight)
+            .map_or(TraitLevel::default(), |&(level, _)| level)
+    }
+
+    // weights are multiplied by this then halved, rounding up so a low trait never rules anything out.
+    // an average trait leaves them as they are
+    fn factor(&self) -> usize {
+        match self {
+            TraitLevel::Low => 1,
+            TraitLevel::Average => 2,
+            TraitLevel::High => 3,
+        }
+    }
+
+    // the opposite factor, for traits that make something less likely
+    fn inverse_factor(&self) -> usize {
+        4 - self.factor()
+    }
+}
+
+impl Personality {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Personality {
+            wanderlust: TraitLevel::new(rng),
+            sociability: TraitLevel::new(rng),
+            greed: TraitLevel::new(rng),
+            caution: TraitLevel::new(rng),
+            sentimentality: TraitLevel::new(rng),
+        }
+    }
+
+    // the weight of moving on when there's no calamity to flee
+    pub fn move_weight(&self, weight: usize) -> usize {
+        (weight * self.wanderlust.factor()).div_ceil(2)
+    }
+
+    // the extra weight of fleeing a calamity
+    pub fn flee_weight(&self, weight: usize) -> usize {
+        (weight * self.caution.factor()).div_ceil(2)
+    }
+
+    pub fn encounter_weight(&self, weight: usize) -> usize {
+        (weight * self.sociability.factor()).div_ceil(2)
+    }
+
+    pub fn idle_weight(&self, weight: usize) -> usize {
+        (weight * self.caution.factor()).div_ceil(2)
+    }
+
+    // the chance of having an accident on the road. careful people have fewer
+    pub fn accident_chance(&self, chance: f64) -> f64 {
+        (chance * self.caution.inverse_factor() as f64 / 2.0).min(1.0)
+    }
+
+    // the chance of giving an item away when meeting someone. greedy people hold on to things,
+    // and sentimental people hold on to things they made
+    pub fn giving_chance(&self, chance: f32, made_it: bool) -> f32 {
+        let mut chance = chance * self.greed.inverse_factor() as f32 / 2.0;
+        if made_it {
+            chance *= self.sentimentality.inverse_factor() as f32 / 2.0;
+        }
+        chance.min(1.0)
+    }
+
+    // the traits the character shows strongly, which the shopkeeper might bring up
+    pub fn strong_traits(&self) -> Vec<PersonalityTrait> {
+        [
+            (PersonalityTrait::Wanderlust, self.wanderlust),
+            (PersonalityTrait::Sociability, self.sociability),
+            (PersonalityTrait::Greed, self.greed),
+            (PersonalityTrait::Caution, self.caution),
+            (PersonalityTrait::Sentimentality, self.sentimentality),
+        ]
+        .into_iter()
+        .filter(|&(_, level)| level == TraitLevel::High)
+        .map(|(personality_trait, _)| personality_trait)
+        .collect()
+    }
+}
+
+impl std::fmt::Display for PersonalityTrait {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PersonalityTrait::Wanderlust => write!(f, "restless"),
+            PersonalityTrait::Sociability => write!(f, "sociable"),
+            PersonalityTrait::Greed => write!(f, "greedy"),
+            PersonalityTrait::Caution => write!(f, "cautious"),
+            PersonalityTrait::Sentimentality => write!(f, "sentimental"),
+        }
+    }
+}
+
 // -- Events --
 
 // event types (the float is used for probability)
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum EventType {
-    EventMove,             // an event representing moving from one city to another
-    EventDeath,            // an event representing the death of a character.
+    EventMove,                // an event representing moving from one city to another
+    EventDeath(DeathCause), // an event representing the death of a character, and what killed them
     EventEncounter, // an event representing a fleeting encounter between two people. An alive character could encounter a dead character. during an encounter, there is a chance for an item to change hands
     EventCreation(ItemID), // an event representing the creating of an item
     EventIdle, // an event representing doing nothing. this event should not be logged in event lists
     EventBirth, // an event representing a character's birth
-               // EventMoveTogether, // an event representing two characters moving together for a while.
-               // add more!
+    EventFounding(CityID), // an event representing a new city being founded
+    EventAbandonment(CityID), // an event representing a city being abandoned to the calamity
+    EventRenaming(CityID), // an event representing a city taking a new name
+    EventRuin(CityID), // an event representing an abandoned city falling into ruin
+    EventLoss(ItemID), // an event representing a character losing an item to the calamity
+    EventCalamityArrival(CityID, CalamityKind), // an event representing a calamity reaching a city
+    EventCityFall(CityID, CalamityKind), // an event representing the last person in a city dying or fleeing from a calamity
+    EventCalamityRecession(CityID, CalamityKind), // an event representing a calamity leaving a city
+                                         // EventMoveTogether, // an event representing two characters moving together for a while.
+                                         // add more!
+}
+
+// what a character died of
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DeathCause {
+    Calamity, // whichever calamity was in the city at the time
+    OldAge,
+    Illness,
+    Accident, // on the road
+    Violence, // at the hands of a rival
+}
+
+impl std::fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeathCause::Calamity => write!(f, "to the calamity"),
+            DeathCause::OldAge => write!(f, "of old age"),
+            DeathCause::Illness => write!(f, "of an illness"),
+            DeathCause::Accident => write!(f, "in an accident"),
+            DeathCause::Violence => write!(f, "by violence"),
+        }
+    }
 }
 
 // An event that has a start time and maybe an end time.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Event {
     pub characters: Vec<CharacterID>, // characters in the event
     pub start_time: usize,
@@ -868,7 +2095,7 @@ impl Event {
     }
 
     // TODO: to be tested when events are added into world gen
-    pub fn add_event_during(&mut self, world: World, event_id: EventID) {
+    pub fn add_event_during(&mut self, world: &World, event_id: EventID) -> Result<(), WorldError> {
         match world.events.get(&event_id) {
             Some(&ref event) => {
                 let valid: bool = durations_overlap(
@@ -880,16 +2107,15 @@ impl Event {
                 if valid {
                     self.events_happening_during.push(event_id);
                 }
+                Ok(())
             }
-            None => match event_id {
-                EventID(id) => panic!("No event found associated with eventID {}", id),
-            },
+            None => Err(WorldError::MissingEvent(event_id)),
         }
     }
 }
 
 // the types of items
-#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, Hash, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum ItemType {
     Teapot1,
     Teapot2,
@@ -898,10 +2124,10 @@ pub enum ItemType {
     Vase2,
     Vase3,
     Cup1,
-    
+
     Orb,
     Statue,
-    
+
     Belt,
     Bracelet,
     Hat,
@@ -909,7 +2135,7 @@ pub enum ItemType {
     Shoes2,
     Shoes3,
     Sunglasses,
-    Necklace
+    Necklace,
 }
 
 impl std::fmt::Display for ItemType {
@@ -931,7 +2157,7 @@ impl std::fmt::Display for ItemType {
             ItemType::Shoes3 => write!(f, "shoes3"),
             ItemType::Statue => write!(f, "statue1"),
             ItemType::Sunglasses => write!(f, "sunglasses1"),
-            ItemType::Necklace => write!(f, "necklace1")
+            ItemType::Necklace => write!(f, "necklace1"),
         }
     }
 }
@@ -952,17 +2178,55 @@ const LIST_ITEM_TYPES: [ItemType; NUM_ITEM_TYPES] = [
     ItemType::Shoes1,
     ItemType::Shoes2,
     ItemType::Sunglasses,
-    ItemType::Necklace
+    ItemType::Necklace,
 ];
 
+// how much more likely an item type is to be made in a city with the right craft
+const CRAFT_ITEM_WEIGHT: usize = 3;
+
 impl ItemType {
-    pub fn new(rng: &mut ThreadRng) -> Self {
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
         LIST_ITEM_TYPES.choose(rng).unwrap().clone()
     }
+
+    // picks an item type, favouring the things the city is known for making
+    pub fn made_in<R: Rng + ?Sized>(traits: &CityTraits, rng: &mut R) -> Self {
+        *LIST_ITEM_TYPES
+            .choose_weighted(rng, |item_type| {
+                if item_type.craft() == traits.craft {
+                    CRAFT_ITEM_WEIGHT
+                } else {
+                    1
+                }
+            })
+            .unwrap()
+    }
+
+    // the craft that makes this kind of item
+    pub fn craft(&self) -> Craft {
+        match self {
+            ItemType::Teapot1
+            | ItemType::Teapot2
+            | ItemType::Teapot3
+            | ItemType::Vase1
+            | ItemType::Vase2
+            | ItemType::Vase3
+            | ItemType::Cup1
+            | ItemType::Orb => Craft::Pottery,
+            ItemType::Belt
+            | ItemType::Hat
+            | ItemType::Shoes1
+            | ItemType::Shoes2
+            | ItemType::Shoes3 => Craft::Textiles,
+            ItemType::Statue | ItemType::Bracelet | ItemType::Sunglasses | ItemType::Necklace => {
+                Craft::Metalwork
+            }
+        }
+    }
 }
 
 // tracks a single move
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ItemMoveRecord {
     pub time: usize,
     pub new_owner: Option<CharacterID>,
@@ -971,20 +2235,19 @@ pub struct ItemMoveRecord {
 }
 
 impl ItemMoveRecord {
-    pub fn expect_owner(&self) -> CharacterID {
-        self.new_owner
-            .expect("Record was expected to have an associated owner")
+    pub fn owner(&self) -> Result<CharacterID, WorldError> {
+        self.new_owner.ok_or(WorldError::IncompleteRecord("owner"))
     }
-    pub fn expect_location(&self) -> CityID {
+    pub fn location(&self) -> Result<CityID, WorldError> {
         self.new_location
-            .expect("Record was expected to have an associated location")
+            .ok_or(WorldError::IncompleteRecord("location"))
     }
-    pub fn expect_event(&self) -> EventID {
-        self.event
-            .expect("Record was expected to have an associated event")
+    pub fn event_id(&self) -> Result<EventID, WorldError> {
+        self.event.ok_or(WorldError::IncompleteRecord("event"))
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Item {
     pub item_type: ItemType,
     pub owner_records: Vec<ItemMoveRecord>,